@@ -0,0 +1,9 @@
+CREATE TABLE IF NOT EXISTS guild_bind (
+    guild_id INTEGER NOT NULL PRIMARY KEY,
+    bind_id INTEGER NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS update_message (
+    guild_id INTEGER NOT NULL,
+    msg_id INTEGER NOT NULL
+)
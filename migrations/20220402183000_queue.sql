@@ -0,0 +1,9 @@
+CREATE TABLE queue_entry (
+    guild_id INTEGER NOT NULL,
+    position INTEGER NOT NULL,
+    url TEXT NOT NULL,
+    title TEXT,
+    requester_id INTEGER NOT NULL,
+    added_at INTEGER NOT NULL,
+    PRIMARY KEY (guild_id, position)
+)
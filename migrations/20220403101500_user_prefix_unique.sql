@@ -0,0 +1,8 @@
+-- a prefix can only be owned by one user. Before, several users could add the same prefix,
+-- those duplicates get DELETED here: the oldest row (the first user that added the prefix)
+-- keeps it, every later user loses that prefix and has to pick another one
+DELETE FROM user_prefix WHERE rowid NOT IN (
+    SELECT MIN(rowid) FROM user_prefix GROUP BY string
+);
+
+CREATE UNIQUE INDEX user_prefix_string ON user_prefix (string)
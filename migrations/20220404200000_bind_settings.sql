@@ -0,0 +1,3 @@
+ALTER TABLE guild_bind ADD COLUMN delete_commands_after INTEGER;
+ALTER TABLE guild_bind ADD COLUMN delete_replies_after INTEGER;
+ALTER TABLE guild_bind ADD COLUMN purge_chatter BOOLEAN NOT NULL DEFAULT 0
@@ -0,0 +1,9 @@
+CREATE TABLE mensa_preference (
+    user_id INTEGER PRIMARY KEY NOT NULL,
+    vegan BOOLEAN NOT NULL DEFAULT 0,
+    vegetarian BOOLEAN NOT NULL DEFAULT 0,
+    no_pork BOOLEAN NOT NULL DEFAULT 0,
+    allergens TEXT NOT NULL DEFAULT '',
+    price_tier TEXT NOT NULL DEFAULT 'student',
+    hide_filtered BOOLEAN NOT NULL DEFAULT 0
+)
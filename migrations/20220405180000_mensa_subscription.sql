@@ -0,0 +1,7 @@
+CREATE TABLE mensa_subscription (
+    guild_id INTEGER PRIMARY KEY NOT NULL,
+    channel_id INTEGER NOT NULL,
+    post_time TEXT NOT NULL,
+    post_closed BOOLEAN NOT NULL DEFAULT 0,
+    last_posted TEXT
+)
@@ -0,0 +1,6 @@
+ALTER TABLE mensa_preference ADD COLUMN location INTEGER;
+ALTER TABLE mensa_subscription ADD COLUMN location INTEGER;
+CREATE TABLE guild_mensa_location (
+    guild_id INTEGER PRIMARY KEY NOT NULL,
+    location INTEGER NOT NULL
+)
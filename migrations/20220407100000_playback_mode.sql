@@ -0,0 +1,5 @@
+CREATE TABLE guild_playback (
+    guild_id INTEGER PRIMARY KEY NOT NULL,
+    loop_mode TEXT NOT NULL DEFAULT 'off',
+    autoplay BOOLEAN NOT NULL DEFAULT 0
+)
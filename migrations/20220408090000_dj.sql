@@ -0,0 +1,5 @@
+CREATE TABLE guild_dj (
+    guild_id INTEGER PRIMARY KEY NOT NULL,
+    role_id INTEGER,
+    skip_percent INTEGER NOT NULL DEFAULT 50
+)
@@ -0,0 +1,15 @@
+CREATE TABLE playlist (
+    playlist_id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+    user_id INTEGER NOT NULL,
+    name TEXT NOT NULL,
+    share_code TEXT UNIQUE,
+    created_at INTEGER NOT NULL,
+    UNIQUE (user_id, name)
+);
+CREATE TABLE playlist_entry (
+    playlist_id INTEGER NOT NULL,
+    position INTEGER NOT NULL,
+    url TEXT NOT NULL,
+    title TEXT,
+    PRIMARY KEY (playlist_id, position)
+)
@@ -0,0 +1,7 @@
+-- names are looked up case insensitively, so they have to be unique that way too. Playlists
+-- that only differ in case get the id appended, the oldest one keeps its name
+UPDATE playlist SET name = name || ' (' || playlist_id || ')' WHERE playlist_id NOT IN (
+    SELECT MIN(playlist_id) FROM playlist GROUP BY user_id, name COLLATE NOCASE
+);
+
+CREATE UNIQUE INDEX playlist_user_name ON playlist (user_id, name COLLATE NOCASE)
@@ -0,0 +1,1024 @@
+use std::io::{Cursor, Read, Write};
+use std::net::IpAddr;
+use std::path::Path;
+use std::{future::Future, pin::Pin, sync::Arc};
+
+use futures::stream::{self, StreamExt};
+use itertools::Itertools;
+use once_cell::sync::Lazy;
+use poise::serenity_prelude::{self as serenity, read_image, CreateEmbed};
+use regex::Regex;
+use tokio::{fs::File, io::AsyncWriteExt};
+use url::Url;
+
+use crate::menu::{self, Control, Menu, MenuComponent};
+use crate::model::image_processing::{image_extension, reduce_emote_size, ReducedEmote};
+use crate::model::temp::{sanitize_filename, TempDir};
+use crate::utils::guild_only;
+use crate::{error::*, Context, Error};
+
+// discord doesn't allow more options in a select menu
+const MAX_SELECT_OPTIONS: usize = 25;
+// limits for downloads and archives so nobody can fill the disk
+const MAX_DOWNLOAD_SIZE: u64 = 10_000_000;
+const MAX_ZIP_IMAGES: usize = 100;
+// upload limit of guilds without boosts
+const MAX_UPLOAD_SIZE: u64 = 8_000_000;
+const EMOTE_PAGE_SIZE: usize = 20;
+// images the export downloads at the same time
+const EXPORT_DOWNLOADS: usize = 4;
+
+static EMOTE_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"<(a?):(\w{2,32}):(\d+)>").expect("invalid emote regex"));
+
+/// a custom emote that was used in a message
+#[derive(Clone)]
+pub struct EmoteRef {
+    pub name: String,
+    pub id: u64,
+    pub animated: bool,
+}
+
+impl EmoteRef {
+    pub fn url(&self) -> String {
+        format!(
+            "https://cdn.discordapp.com/emojis/{}.{}",
+            self.id,
+            self.extension()
+        )
+    }
+
+    pub fn extension(&self) -> &'static str {
+        if self.animated {
+            "gif"
+        } else {
+            "png"
+        }
+    }
+}
+
+/// finds every custom emote in the text, each emote only once
+pub fn parse_emotes(text: &str) -> Vec<EmoteRef> {
+    let mut emotes: Vec<EmoteRef> = vec![];
+    for cap in EMOTE_REGEX.captures_iter(text) {
+        let id = match cap[3].parse() {
+            Ok(id) => id,
+            Err(_) => continue,
+        };
+        if emotes.iter().all(|emote| emote.id != id) {
+            emotes.push(EmoteRef {
+                name: cap[2].to_string(),
+                id,
+                animated: !cap[1].is_empty(),
+            });
+        }
+    }
+    emotes
+}
+
+#[poise::command(
+    prefix_command,
+    slash_command,
+    track_edits,
+    category = "General",
+    required_permissions = "MANAGE_EMOJIS_AND_STICKERS"
+)]
+pub(crate) async fn addemote(
+    ctx: Context<'_>,
+    #[description = "Name of the emote"] emote_name: String,
+    #[description = "The emote that gets added"] emote: Option<serenity::Attachment>,
+    #[description = "Image url or custom emote instead of an upload"] source: Option<String>,
+) -> Result<(), Error> {
+    ctx.defer_or_broadcast().await?;
+    let guild_id = ctx.guild_id().ok_or_else(|| Error::Input(NOT_IN_GUILD))?;
+    let (filename, file) = match (emote, source) {
+        (Some(emote), _) => (emote.filename.clone(), emote.download().await?),
+        (None, Some(source)) => download_source(&source).await?,
+        (None, None) => return Err(Error::Input(NO_EMOTE_SOURCE)),
+    };
+    let (emote, reduced) = create_emote(&ctx, guild_id, &emote_name, &filename, &file).await?;
+    ctx.say(emote_summary(&emote, &reduced)).await?;
+
+    Ok(())
+}
+
+/// downloads a custom emote or an image url, returns the filename and the image
+async fn download_source(source: &str) -> Result<(String, Vec<u8>), Error> {
+    let (url, filename) = match parse_emotes(source).first() {
+        Some(emote) => (emote.url(), format!("{}.{}", emote.id, emote.extension())),
+        None => {
+            let url = Url::parse(source.trim()).map_err(|_| Error::Input(INVALID_EMOTE_SOURCE))?;
+            if url.scheme() != "http" && url.scheme() != "https" {
+                return Err(Error::Input(INVALID_EMOTE_SOURCE));
+            }
+            let filename = url
+                .path_segments()
+                .and_then(|segments| segments.last())
+                .filter(|name| !name.is_empty())
+                .unwrap_or("emote")
+                .to_string();
+            (url.to_string(), filename)
+        }
+    };
+    Ok((filename, download_image(&url).await?))
+}
+
+/// downloads an image of a public host, at most `MAX_DOWNLOAD_SIZE` bytes
+async fn download_image(url: &str) -> Result<Vec<u8>, Error> {
+    let url = Url::parse(url).map_err(|_| Error::Input(INVALID_EMOTE_SOURCE))?;
+    let host = url
+        .host_str()
+        .ok_or_else(|| Error::Input(INVALID_EMOTE_SOURCE))?
+        .to_string();
+    let port = url
+        .port_or_known_default()
+        .ok_or_else(|| Error::Input(INVALID_EMOTE_SOURCE))?;
+    // the url comes from users, so it must not reach the network the bot runs in
+    let addrs = tokio::net::lookup_host((host.trim_matches(|c| c == '[' || c == ']'), port))
+        .await
+        .map_err(|_| Error::Input(INVALID_EMOTE_SOURCE))?
+        .collect::<Vec<_>>();
+    let addr = match addrs.first() {
+        Some(addr) if addrs.iter().all(|addr| is_public(addr.ip())) => *addr,
+        _ => return Err(Error::Input(PRIVATE_EMOTE_SOURCE)),
+    };
+    // pin the checked address and don't follow redirects to somewhere else
+    let client = reqwest::Client::builder()
+        .resolve(&host, addr)
+        .redirect(reqwest::redirect::Policy::none())
+        .build()?;
+    let mut response = client.get(url).send().await?.error_for_status()?;
+    let is_image = response
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .map_or(false, |value| value.starts_with("image/"));
+    if !is_image {
+        return Err(Error::Input(NOT_AN_IMAGE));
+    }
+    if response
+        .content_length()
+        .map_or(false, |len| len > MAX_DOWNLOAD_SIZE)
+    {
+        return Err(Error::Input(FILE_TOO_LARGE));
+    }
+    // the content length can be missing or wrong, so the limit is checked while reading
+    let mut image = vec![];
+    while let Some(chunk) = response.chunk().await? {
+        if (image.len() + chunk.len()) as u64 > MAX_DOWNLOAD_SIZE {
+            return Err(Error::Input(FILE_TOO_LARGE));
+        }
+        image.extend_from_slice(&chunk);
+    }
+    Ok(image)
+}
+
+/// whether the address can be reached from the internet, `IpAddr::is_global` isn't stable yet
+fn is_public(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [a, b, ..] = ip.octets();
+            !(ip.is_private()
+                || ip.is_loopback()
+                || ip.is_link_local()
+                || ip.is_broadcast()
+                || ip.is_documentation()
+                || ip.is_unspecified()
+                || ip.is_multicast()
+                // shared address space of carrier grade nat
+                || (a == 100 && (64..128).contains(&b))
+                || a == 0)
+        }
+        IpAddr::V6(ip) => {
+            if let Some(ip) = ip.to_ipv4() {
+                // ipv4 mapped and compatible addresses, `::1` is handled as loopback below
+                if !ip.is_unspecified() && ip.octets() != [0, 0, 0, 1] {
+                    return is_public(IpAddr::V4(ip));
+                }
+            }
+            let first = ip.segments()[0];
+            !(ip.is_loopback()
+                || ip.is_unspecified()
+                || ip.is_multicast()
+                // unique local
+                || (first & 0xfe00) == 0xfc00
+                // link local
+                || (first & 0xffc0) == 0xfe80)
+        }
+    }
+}
+
+/// adds every image of a zip archive as emote, named after the file
+#[poise::command(
+    prefix_command,
+    slash_command,
+    category = "General",
+    required_permissions = "MANAGE_EMOJIS_AND_STICKERS"
+)]
+pub(crate) async fn importemotes(
+    ctx: Context<'_>,
+    #[description = "Zip archive with the emotes"] archive: serenity::Attachment,
+) -> Result<(), Error> {
+    ctx.defer_or_broadcast().await?;
+    let guild = ctx.guild().ok_or_else(|| Error::Input(NOT_IN_GUILD))?;
+    if archive.size > MAX_DOWNLOAD_SIZE {
+        return Err(Error::Input(FILE_TOO_LARGE));
+    }
+    let bytes = archive.download().await?;
+    let entries = tokio::task::block_in_place(|| read_zip_images(&bytes))?;
+    if entries.is_empty() {
+        return Err(Error::Input(NO_IMAGES_IN_ZIP));
+    }
+
+    let limit = emoji_limit(guild.premium_tier);
+    let animated = guild.emojis.values().filter(|e| e.animated).count();
+    let mut static_left = limit.saturating_sub(guild.emojis.len() - animated);
+    let mut animated_left = limit.saturating_sub(animated);
+
+    let mut report = vec![];
+    for (filename, image) in entries {
+        let name = match emote_name(&filename) {
+            Some(name) => name,
+            None => {
+                report.push(format!("❌ `{}`: {}", filename, INVALID_EMOTE_NAME));
+                continue;
+            }
+        };
+        // removed with the shrunk versions at the end of the iteration
+        let dir = TempDir::new(ctx.id())?;
+        let reduced = match reduce_image(&dir, &filename, &image).await {
+            Ok(reduced) => reduced,
+            Err(why) => {
+                report.push(format!("❌ `{}`: {}", filename, why));
+                continue;
+            }
+        };
+        // shrinking can turn an animation into a still image
+        let slots = if reduced.animated {
+            &mut animated_left
+        } else {
+            &mut static_left
+        };
+        if *slots == 0 {
+            report.push(format!("❌ `{}`: {}", filename, NO_EMOTE_SLOTS));
+            continue;
+        }
+        match upload_emote(&ctx, guild.id, &name, reduced).await {
+            Ok((emote, reduced)) => {
+                *slots -= 1;
+                report.push(format!(
+                    "✅ `{}`: {}",
+                    filename,
+                    emote_summary(&emote, &reduced)
+                ));
+            }
+            Err(why) => report.push(format!("❌ `{}`: {}", filename, why)),
+        }
+    }
+
+    // a message can't have more than 2000 characters
+    let mut msg = String::new();
+    for line in report {
+        if msg.len() + line.len() + 1 > 2000 {
+            ctx.say(std::mem::take(&mut msg)).await?;
+        }
+        msg.push_str(&line);
+        msg.push('\n');
+    }
+    if !msg.is_empty() {
+        ctx.say(msg).await?;
+    }
+    Ok(())
+}
+
+/// reads every supported image of the archive, returns the file names with the images
+fn read_zip_images(bytes: &[u8]) -> Result<Vec<(String, Vec<u8>)>, Error> {
+    let mut archive =
+        zip::ZipArchive::new(Cursor::new(bytes)).map_err(|_| Error::Input(INVALID_ZIP))?;
+    let mut images = vec![];
+    for i in 0..archive.len() {
+        let mut file = archive.by_index(i).map_err(|_| Error::Input(INVALID_ZIP))?;
+        let path = match file.enclosed_name() {
+            Some(path) if file.is_file() => path.to_owned(),
+            _ => continue,
+        };
+        let filename = match path.file_name().and_then(|name| name.to_str()) {
+            // skip metadata of macos and other hidden files
+            Some(name) if !name.starts_with('.') && !path.starts_with("__MACOSX") => {
+                name.to_string()
+            }
+            _ => continue,
+        };
+        let extension = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .map(|ext| ext.to_lowercase());
+        if !matches!(
+            extension.as_deref(),
+            Some("png") | Some("jpg") | Some("jpeg") | Some("gif") | Some("webp")
+        ) {
+            continue;
+        }
+        if images.len() >= MAX_ZIP_IMAGES {
+            break;
+        }
+        // don't trust the size in the header, zip bombs lie about it
+        let mut image = vec![];
+        (&mut file)
+            .take(MAX_DOWNLOAD_SIZE + 1)
+            .read_to_end(&mut image)?;
+        if image.len() as u64 > MAX_DOWNLOAD_SIZE {
+            continue;
+        }
+        images.push((filename, image));
+    }
+    Ok(images)
+}
+
+/// the file stem as emote name, `None` if discord wouldn't accept it
+fn emote_name(filename: &str) -> Option<String> {
+    let stem = Path::new(filename).file_stem()?.to_str()?;
+    let name = stem
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect::<String>();
+    if (2..=32).contains(&name.len()) {
+        Some(name)
+    } else {
+        None
+    }
+}
+
+/// the amount of static emotes a guild can have, animated emotes have their own slots
+fn emoji_limit(tier: serenity::PremiumTier) -> usize {
+    match tier {
+        serenity::PremiumTier::Tier1 => 100,
+        serenity::PremiumTier::Tier2 => 150,
+        serenity::PremiumTier::Tier3 => 250,
+        _ => 50,
+    }
+}
+
+/// shrinks the image if needed and adds it as emote to the guild
+pub async fn create_emote(
+    ctx: &Context<'_>,
+    guild_id: serenity::GuildId,
+    name: &str,
+    filename: &str,
+    image: &[u8],
+) -> Result<(serenity::Emoji, ReducedEmote), Error> {
+    // removed with the shrunk versions when it goes out of scope
+    let dir = TempDir::new(ctx.id())?;
+    let reduced = reduce_image(&dir, filename, image).await?;
+    upload_emote(ctx, guild_id, name, reduced).await
+}
+
+/// writes the image into the directory and shrinks it until discord takes it as emote
+async fn reduce_image(dir: &TempDir, filename: &str, image: &[u8]) -> Result<ReducedEmote, Error> {
+    let path = dir.file(filename);
+    let mut buf = File::create(&path).await?;
+    buf.write_all(image).await?;
+    // encoding animations takes a while, so don't block the other tasks of this worker
+    tokio::task::block_in_place(|| reduce_emote_size(&path))
+}
+
+async fn upload_emote(
+    ctx: &Context<'_>,
+    guild_id: serenity::GuildId,
+    name: &str,
+    reduced: ReducedEmote,
+) -> Result<(serenity::Emoji, ReducedEmote), Error> {
+    let emote = guild_id
+        .create_emoji(&ctx.discord().http, name, &(read_image(&reduced.path)?))
+        .await?;
+    Ok((emote, reduced))
+}
+
+fn emote_summary(emote: &serenity::Emoji, reduced: &ReducedEmote) -> String {
+    format!(
+        "{} ({}x{}, {} KB)",
+        emote,
+        reduced.width,
+        reduced.height,
+        reduced.size / 1000
+    )
+}
+
+/// something of a message that can be added to the guild
+enum Stealable {
+    Emote(EmoteRef),
+    Sticker(serenity::StickerItem),
+}
+
+impl Stealable {
+    fn name(&self) -> &str {
+        match self {
+            Stealable::Emote(emote) => &emote.name,
+            Stealable::Sticker(sticker) => &sticker.name,
+        }
+    }
+}
+
+struct StealMenu {
+    picked: Vec<usize>,
+}
+
+#[poise::command(
+    context_menu_command = "steal emotes",
+    required_permissions = "MANAGE_EMOJIS_AND_STICKERS"
+)]
+pub(crate) async fn steal_emote(ctx: Context<'_>, msg: serenity::Message) -> Result<(), Error> {
+    steal_from(ctx, &msg, None).await
+}
+
+/// steal emotes and stickers from the message you reply to
+#[poise::command(
+    prefix_command,
+    category = "General",
+    required_permissions = "MANAGE_EMOJIS_AND_STICKERS"
+)]
+pub(crate) async fn steal(
+    ctx: Context<'_>,
+    #[description = "new name, only used if one emote gets picked"] name: Option<String>,
+) -> Result<(), Error> {
+    let msg = match ctx {
+        poise::Context::Prefix(prefix_ctx) => prefix_ctx.msg.referenced_message.clone(),
+        poise::Context::Application(_) => None,
+    }
+    .ok_or_else(|| Error::Input(NOT_A_REPLY))?;
+    steal_from(ctx, &msg, name).await
+}
+
+async fn steal_from(
+    ctx: Context<'_>,
+    msg: &serenity::Message,
+    name: Option<String>,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or_else(|| Error::Input(NOT_IN_GUILD))?;
+    let items = parse_emotes(&msg.content)
+        .into_iter()
+        .map(Stealable::Emote)
+        .chain(
+            msg.sticker_items
+                .iter()
+                // lottie stickers can only be added by discord itself
+                .filter(|sticker| sticker.format_type != serenity::StickerFormatType::Lottie)
+                .cloned()
+                .map(Stealable::Sticker),
+        )
+        .take(MAX_SELECT_OPTIONS)
+        .collect::<Vec<_>>();
+    if items.is_empty() {
+        return Err(Error::Input(NOTHING_TO_STEAL));
+    }
+
+    let picked = if items.len() == 1 {
+        vec![0]
+    } else {
+        pick_stealables(&ctx, items.iter().map(|item| item.name()).collect()).await?
+    };
+    if picked.is_empty() {
+        return Ok(());
+    }
+    ctx.defer_or_broadcast().await?;
+
+    let name = if picked.len() == 1 { name } else { None };
+    let mut report = vec![];
+    for index in picked {
+        let item = match items.get(index) {
+            Some(item) => item,
+            None => continue,
+        };
+        let result = match item {
+            Stealable::Emote(emote) => {
+                add_stolen_emote(&ctx, guild_id, emote, name.as_deref()).await
+            }
+            Stealable::Sticker(sticker) => {
+                add_stolen_sticker(&ctx, guild_id, sticker, name.as_deref()).await
+            }
+        };
+        report.push(match result {
+            Ok(line) => line,
+            Err(why) => format!("couldn't add `{}`: {}", item.name(), why),
+        });
+    }
+    ctx.say(report.join("\n")).await?;
+    Ok(())
+}
+
+async fn add_stolen_emote(
+    ctx: &Context<'_>,
+    guild_id: serenity::GuildId,
+    emote: &EmoteRef,
+    name: Option<&str>,
+) -> Result<String, Error> {
+    let image = download_image(&emote.url()).await?;
+    let filename = format!("{}.{}", emote.id, emote.extension());
+    let (emote, reduced) = create_emote(
+        ctx,
+        guild_id,
+        name.unwrap_or(&emote.name),
+        &filename,
+        &image,
+    )
+    .await?;
+    Ok(emote_summary(&emote, &reduced))
+}
+
+async fn add_stolen_sticker(
+    ctx: &Context<'_>,
+    guild_id: serenity::GuildId,
+    sticker: &serenity::StickerItem,
+    name: Option<&str>,
+) -> Result<String, Error> {
+    // lottie stickers are json animations, not images
+    if sticker.format_type == serenity::StickerFormatType::Lottie {
+        return Err(Error::Input(UNSUPPORTED_STICKER_FORMAT));
+    }
+    let url = sticker
+        .image_url()
+        .ok_or_else(|| Error::Input(NOTHING_TO_STEAL))?;
+    let image = download_image(&url).await?;
+    // apng and gif stickers have to keep their format to stay animated
+    let extension = image_extension(&image).unwrap_or("png");
+    let dir = TempDir::new(ctx.id())?;
+    let reduced = reduce_image(&dir, &format!("{}.{}", sticker.id, extension), &image).await?;
+    let extension = reduced
+        .path
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .unwrap_or(extension);
+    // opaque images can end up as jpeg, which discord doesn't take for stickers
+    if extension != "png" && extension != "gif" {
+        return Err(Error::Input(UNSUPPORTED_STICKER_FORMAT));
+    }
+    let data = tokio::fs::read(&reduced.path).await?;
+    let name = name.unwrap_or(&sticker.name);
+    let sticker = guild_id
+        .create_sticker(&ctx.discord().http, |s| {
+            s.name(name)
+                .tags(name)
+                .description(format!("stolen {}", name))
+                .file(serenity::AttachmentType::Bytes {
+                    data: data.into(),
+                    filename: format!("{}.{}", sticker.id, extension),
+                })
+        })
+        .await?;
+    Ok(format!(
+        "added sticker `{}` ({}x{}, {} KB)",
+        sticker.name,
+        reduced.width,
+        reduced.height,
+        reduced.size / 1000
+    ))
+}
+
+/// lets the author pick which of the emotes and stickers get added
+async fn pick_stealables(ctx: &Context<'_>, names: Vec<&str>) -> Result<Vec<usize>, Error> {
+    let mut menu = Menu::new(ctx, StealMenu { picked: vec![] }, |options| {
+        options
+            .add_row(|row| {
+                row.add_button(Control::new(
+                    MenuComponent::select("steal_pick", |s| {
+                        s.placeholder("pick what gets added")
+                            .min_values(1)
+                            .max_values(names.len() as u64)
+                            .options(|o| {
+                                for (i, name) in names.iter().enumerate() {
+                                    o.create_option(|op| op.label(name).value(i));
+                                }
+                                o
+                            })
+                    }),
+                    Arc::new(steal_pick),
+                ))
+            })
+            .set_post_hook(Arc::new(steal_close))
+    });
+    menu.run(|m| m.content("which ones should be added?"))
+        .await?;
+    Ok(menu.data.picked)
+}
+
+fn steal_pick<'a>(
+    menu: &'a mut Menu<'_, StealMenu>,
+    mci: &'a Arc<serenity::MessageComponentInteraction>,
+) -> Pin<Box<dyn Future<Output = Result<(), Error>> + 'a + Send>> {
+    Box::pin(async move {
+        menu.data.picked = mci
+            .data
+            .values
+            .iter()
+            .filter_map(|value| value.parse::<usize>().ok())
+            .collect();
+        menu.stop();
+        Ok(())
+    })
+}
+
+fn steal_close<'a>(
+    menu: &'a mut Menu<'_, StealMenu>,
+) -> Pin<Box<dyn Future<Output = Result<(), Error>> + 'a + Send>> {
+    Box::pin(async move {
+        if let Some(msg_id) = menu.msg_id {
+            // ignore result, message could be deleted already
+            let _ = menu
+                .ctx
+                .channel_id()
+                .delete_message(&menu.ctx.discord().http, msg_id)
+                .await;
+        }
+        Ok(())
+    })
+}
+
+struct EmoteListMenu {
+    pages: Vec<String>,
+    page: usize,
+}
+
+/// manage the emotes of this server
+#[poise::command(
+    slash_command,
+    prefix_command,
+    category = "General",
+    check = "guild_only",
+    required_permissions = "MANAGE_EMOJIS_AND_STICKERS",
+    subcommands("emote_rename", "emote_delete", "emote_list", "emote_export")
+)]
+pub(crate) async fn emote(ctx: Context<'_>) -> Result<(), Error> {
+    emote_list_inner(ctx).await
+}
+
+/// rename an emote of this server
+#[poise::command(
+    slash_command,
+    prefix_command,
+    rename = "rename",
+    check = "guild_only",
+    required_permissions = "MANAGE_EMOJIS_AND_STICKERS"
+)]
+pub(crate) async fn emote_rename(
+    ctx: Context<'_>,
+    #[description = "the emote or its name"]
+    #[autocomplete = "autocomplete_emote"]
+    emote: String,
+    #[description = "the new name"] name: String,
+) -> Result<(), Error> {
+    ctx.defer_or_broadcast().await?;
+    let guild = ctx.guild().ok_or_else(|| Error::Input(NOT_IN_GUILD))?;
+    let old = find_emote(&guild, &emote)?;
+    if emote_name(&name).as_deref() != Some(name.as_str()) {
+        return Err(Error::Input(INVALID_EMOTE_NAME));
+    }
+    let renamed = guild
+        .id
+        .edit_emoji(&ctx.discord().http, old.id, &name)
+        .await?;
+    ctx.say(format!("renamed `{}` to {}", old.name, renamed))
+        .await?;
+    crate::utils::log_to_bound_channel(
+        &ctx,
+        &format!(
+            "{} renamed the emote `{}` to {}",
+            ctx.author().tag(),
+            old.name,
+            renamed
+        ),
+    )
+    .await
+}
+
+/// delete an emote of this server
+#[poise::command(
+    slash_command,
+    prefix_command,
+    rename = "delete",
+    check = "guild_only",
+    required_permissions = "MANAGE_EMOJIS_AND_STICKERS"
+)]
+pub(crate) async fn emote_delete(
+    ctx: Context<'_>,
+    #[description = "the emote or its name"]
+    #[autocomplete = "autocomplete_emote"]
+    emote: String,
+) -> Result<(), Error> {
+    ctx.defer_or_broadcast().await?;
+    let guild = ctx.guild().ok_or_else(|| Error::Input(NOT_IN_GUILD))?;
+    let emote = find_emote(&guild, &emote)?;
+    guild.id.delete_emoji(&ctx.discord().http, emote.id).await?;
+    ctx.say(format!("deleted `{}`", emote.name)).await?;
+    crate::utils::log_to_bound_channel(
+        &ctx,
+        &format!(
+            "{} deleted the emote `{}` ({})",
+            ctx.author().tag(),
+            emote.name,
+            emote.url()
+        ),
+    )
+    .await
+}
+
+/// list the emotes of this server
+#[poise::command(
+    slash_command,
+    prefix_command,
+    rename = "list",
+    check = "guild_only",
+    required_permissions = "MANAGE_EMOJIS_AND_STICKERS"
+)]
+pub(crate) async fn emote_list(ctx: Context<'_>) -> Result<(), Error> {
+    emote_list_inner(ctx).await
+}
+
+async fn emote_list_inner(ctx: Context<'_>) -> Result<(), Error> {
+    ctx.defer_or_broadcast().await?;
+    let guild = ctx.guild().ok_or_else(|| Error::Input(NOT_IN_GUILD))?;
+    let mut emotes = guild.emojis.values().collect::<Vec<_>>();
+    if emotes.is_empty() {
+        return Err(Error::Input(NO_EMOTES));
+    }
+    emotes.sort_by_key(|emote| emote.name.to_lowercase());
+    let pages = emotes
+        .chunks(EMOTE_PAGE_SIZE)
+        .map(|page| {
+            page.iter()
+                .map(|emote| format!("{} `{}`", emote, emote.name))
+                .join("\n")
+        })
+        .collect::<Vec<_>>();
+    let color = ctx.data().config.color()?;
+    let embed = emote_list_embed(&pages, 0, color);
+
+    let mut menu = Menu::new(&ctx, EmoteListMenu { pages, page: 0 }, |options| {
+        options
+            .add_row(|row| {
+                row.add_button(Control::new(
+                    MenuComponent::button("emote_prev", |b| {
+                        b.label("◀").style(serenity::ButtonStyle::Primary)
+                    }),
+                    Arc::new(emote_prev),
+                ))
+                .add_button(Control::new(
+                    MenuComponent::button("emote_next", |b| {
+                        b.label("▶").style(serenity::ButtonStyle::Primary)
+                    }),
+                    Arc::new(emote_next),
+                ))
+            })
+            .set_post_hook(Arc::new(emote_list_close))
+    });
+    menu.run(|m| {
+        m.embed(|e| {
+            e.clone_from(&embed);
+            e
+        })
+    })
+    .await
+}
+
+fn emote_list_embed(pages: &Vec<String>, page: usize, color: serenity::Color) -> CreateEmbed {
+    let cursor = menu::Cursor::at(pages, page);
+    let mut embed = CreateEmbed::default();
+    embed
+        .title("Emotes")
+        .color(color)
+        .description(cursor.current().cloned().unwrap_or_default())
+        .footer(|f| f.text(format!("page {}/{}", cursor.index() + 1, pages.len())));
+    embed
+}
+
+async fn update_emote_list(
+    menu: &mut Menu<'_, EmoteListMenu>,
+    mci: &Arc<serenity::MessageComponentInteraction>,
+    page: impl FnOnce(&mut menu::Cursor<String>),
+) -> Result<(), Error> {
+    let mut cursor = menu::Cursor::at(&menu.data.pages, menu.data.page);
+    page(&mut cursor);
+    menu.data.page = cursor.index();
+    let embed = emote_list_embed(
+        &menu.data.pages,
+        menu.data.page,
+        menu.ctx.data().config.color()?,
+    );
+    menu.update_response(|m| m.set_embed(embed), mci).await
+}
+
+fn emote_prev<'a>(
+    menu: &'a mut Menu<'_, EmoteListMenu>,
+    mci: &'a Arc<serenity::MessageComponentInteraction>,
+) -> Pin<Box<dyn Future<Output = Result<(), Error>> + 'a + Send>> {
+    Box::pin(async move {
+        update_emote_list(menu, mci, |cursor| {
+            cursor.prev();
+        })
+        .await
+    })
+}
+
+fn emote_next<'a>(
+    menu: &'a mut Menu<'_, EmoteListMenu>,
+    mci: &'a Arc<serenity::MessageComponentInteraction>,
+) -> Pin<Box<dyn Future<Output = Result<(), Error>> + 'a + Send>> {
+    Box::pin(async move {
+        update_emote_list(menu, mci, |cursor| {
+            cursor.next();
+        })
+        .await
+    })
+}
+
+fn emote_list_close<'a>(
+    menu: &'a mut Menu<'_, EmoteListMenu>,
+) -> Pin<Box<dyn Future<Output = Result<(), Error>> + 'a + Send>> {
+    Box::pin(async move {
+        if let Some(msg_id) = menu.msg_id {
+            // ignore result, message could be deleted already
+            let _ = menu
+                .ctx
+                .channel_id()
+                .delete_message(&menu.ctx.discord().http, msg_id)
+                .await;
+        }
+        Ok(())
+    })
+}
+
+/// export all emotes and stickers of this server as zip archive
+#[poise::command(
+    slash_command,
+    prefix_command,
+    rename = "export",
+    check = "guild_only",
+    required_permissions = "MANAGE_EMOJIS_AND_STICKERS"
+)]
+pub(crate) async fn emote_export(ctx: Context<'_>) -> Result<(), Error> {
+    ctx.defer_or_broadcast().await?;
+    let guild = ctx.guild().ok_or_else(|| Error::Input(NOT_IN_GUILD))?;
+    // (directory, name without extension, url, extension if it's known already)
+    let mut downloads = vec![];
+    // names can repeat and contain anything, the id keeps them unique and safe to extract
+    for emote in guild.emojis.values() {
+        let extension = if emote.animated { "gif" } else { "png" };
+        let name = format!("{}_{}", emote.name, emote.id);
+        downloads.push(("emotes", name, emote.url(), Some(extension)));
+    }
+    // lottie stickers don't have an image url
+    for sticker in guild.id.stickers(&ctx.discord().http).await? {
+        if let Some(url) = sticker.image_url() {
+            let name = format!("{}_{}", sticker.name, sticker.id);
+            downloads.push(("stickers", name, url, None));
+        }
+    }
+    if downloads.is_empty() {
+        return Err(Error::Input(NO_EMOTES));
+    }
+
+    let mut results = stream::iter(downloads)
+        .map(|(dir, name, url, extension)| async move {
+            download_image(&url)
+                .await
+                .map(|image| (dir, name, extension, image))
+        })
+        .buffer_unordered(EXPORT_DOWNLOADS);
+    let mut files = vec![];
+    let mut total = 0;
+    while let Some(result) = results.next().await {
+        let (dir, name, extension, image) = result?;
+        total += image.len() as u64;
+        // images hardly get smaller in a zip, the archive couldn't be uploaded anyway
+        if total > MAX_UPLOAD_SIZE {
+            return Err(Error::Input(EXPORT_TOO_LARGE));
+        }
+        // apng and gif stickers keep their format
+        let extension = extension
+            .or_else(|| image_extension(&image))
+            .unwrap_or("png");
+        let filename = sanitize_filename(&format!("{}.{}", name, extension));
+        files.push((format!("{}/{}", dir, filename), image));
+    }
+    // the downloads finish in any order
+    files.sort_by(|a, b| a.0.cmp(&b.0));
+
+    let archive = tokio::task::block_in_place(|| write_zip(&files))?;
+    if archive.len() as u64 > MAX_UPLOAD_SIZE {
+        return Err(Error::Input(EXPORT_TOO_LARGE));
+    }
+    ctx.send(|m| {
+        m.content(format!("{} files", files.len()))
+            .attachment(serenity::AttachmentType::Bytes {
+                data: archive.into(),
+                filename: format!("{}.zip", sanitize_filename(&guild.name)),
+            })
+    })
+    .await?;
+    crate::utils::log_to_bound_channel(
+        &ctx,
+        &format!("{} exported the emotes and stickers", ctx.author().tag()),
+    )
+    .await
+}
+
+/// the names have to be unique and sanitized already
+fn write_zip(files: &[(String, impl AsRef<[u8]>)]) -> Result<Vec<u8>, Error> {
+    let mut archive = zip::ZipWriter::new(Cursor::new(vec![]));
+    for (name, data) in files {
+        archive
+            .start_file(name, zip::write::FileOptions::default())
+            .map_err(|_| Error::Failure(EXPORT_FAILED))?;
+        archive.write_all(data.as_ref())?;
+    }
+    Ok(archive
+        .finish()
+        .map_err(|_| Error::Failure(EXPORT_FAILED))?
+        .into_inner())
+}
+
+/// finds the emote of the guild by the emote itself or its name
+fn find_emote(guild: &serenity::Guild, emote: &str) -> Result<serenity::Emoji, Error> {
+    let id = parse_emotes(emote).first().map(|emote| emote.id);
+    let name = emote.trim().trim_matches(':');
+    guild
+        .emojis
+        .values()
+        .find(|emote| Some(emote.id.0) == id || emote.name == name)
+        .cloned()
+        .ok_or_else(|| Error::Input(UNKNOWN_EMOTE))
+}
+
+async fn autocomplete_emote(
+    ctx: Context<'_>,
+    partial: String,
+) -> impl Iterator<Item = poise::AutocompleteChoice<String>> {
+    let partial = partial.to_lowercase();
+    let mut names = ctx
+        .guild()
+        .map(|guild| {
+            guild
+                .emojis
+                .values()
+                .map(|emote| emote.name.clone())
+                .filter(|name| name.to_lowercase().contains(&partial))
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+    names.sort();
+    names
+        .into_iter()
+        .take(MAX_SELECT_OPTIONS)
+        .map(|name| poise::AutocompleteChoice {
+            name: name.clone(),
+            value: name,
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::commands::emote::*;
+
+    fn ip(ip: &str) -> IpAddr {
+        ip.parse().unwrap()
+    }
+
+    #[test]
+    fn test_is_public() {
+        assert!(is_public(ip("162.159.130.233")));
+        assert!(is_public(ip("2606:4700::6810:84e5")));
+        for private in [
+            "127.0.0.1",
+            "10.0.0.1",
+            "172.16.5.4",
+            "192.168.178.1",
+            "169.254.169.254",
+            "100.64.0.1",
+            "0.0.0.0",
+            "::1",
+            "::",
+            "fe80::1",
+            "fd00::1",
+            "::ffff:127.0.0.1",
+            "::ffff:192.168.0.1",
+            "::ffff:169.254.169.254",
+        ] {
+            assert!(!is_public(ip(private)), "{} is public", private);
+        }
+        assert!(is_public(ip("::ffff:162.159.130.233")));
+    }
+
+    #[test]
+    fn test_parse_emotes() {
+        let emotes = parse_emotes("<:pog:123456> text <a:dance:42><:pog:123456> <:x:1> <b:no:2>");
+        let emotes = emotes
+            .iter()
+            .map(|emote| (emote.name.as_str(), emote.id, emote.animated))
+            .collect::<Vec<_>>();
+        assert_eq!(emotes, vec![("pog", 123456, false), ("dance", 42, true)]);
+        assert_eq!(
+            parse_emotes("<a:dance:42>")[0].url(),
+            "https://cdn.discordapp.com/emojis/42.gif"
+        );
+        assert!(parse_emotes("no emotes :pog:").is_empty());
+    }
+}
@@ -1,7 +1,27 @@
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+use std::sync::Arc;
+
 use crate::error::*;
-use crate::utils::guild_only;
-use crate::{Context, Error};
+use crate::utils::{guild_only, is_unique_violation};
+use crate::{Context, Data, Error};
+use once_cell::sync::Lazy;
 use poise::serenity_prelude as serenity;
+use tokio::sync::{mpsc, Mutex};
+use tokio::time::{Duration, Instant};
+use tracing::error;
+
+const MAX_PREFIX_LEN: usize = 10;
+// discord deletes at most 100 messages at once
+const BULK_DELETE_LIMIT: usize = 100;
+
+type CleanupQueue = mpsc::UnboundedSender<(Instant, serenity::MessageId)>;
+
+// messages of the bound channels waiting to be deleted, every channel has one task working
+// through its queue
+static CLEANUP_QUEUES: Lazy<Mutex<HashMap<serenity::ChannelId, CleanupQueue>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+pub const MAX_USER_PREFIXES: usize = 3;
 
 #[poise::command(
     slash_command,
@@ -72,6 +92,557 @@ pub(crate) async fn ping_bind(ctx: Context<'_>) -> Result<(), Error> {
     Ok(())
 }
 
+/// configure how the bound channel is kept clean, shows the settings without arguments
+#[poise::command(
+    slash_command,
+    category = "server management",
+    check = "guild_only",
+    required_permissions = "ADMINISTRATOR",
+    ephemeral
+)]
+pub(crate) async fn bind_settings(
+    ctx: Context<'_>,
+    #[description = "seconds until commands get deleted, 0 disables it"] delete_commands: Option<
+        u64,
+    >,
+    #[description = "seconds until bot replies get deleted, 0 disables it"] delete_replies: Option<
+        u64,
+    >,
+    #[description = "delete messages that aren't commands"] purge_chatter: Option<bool>,
+) -> Result<(), Error> {
+    ctx.defer_ephemeral().await?;
+    let guild_id = ctx.guild_id().ok_or_else(|| Error::Input(NOT_IN_GUILD))?.0 as i64;
+    let database = &ctx.data().database;
+    let mut settings = get_bind_settings(database, guild_id)
+        .await?
+        .ok_or_else(|| Error::Input(NO_BOT_CHANNEL))?;
+    let to_setting = |secs: u64| if secs == 0 { None } else { Some(secs) };
+    if let Some(secs) = delete_commands {
+        settings.delete_commands_after = to_setting(secs);
+    }
+    if let Some(secs) = delete_replies {
+        settings.delete_replies_after = to_setting(secs);
+    }
+    if let Some(purge) = purge_chatter {
+        settings.purge_chatter = purge;
+    }
+    set_bind_settings(database, guild_id, &settings).await?;
+
+    let describe = |secs: Option<u64>| match secs {
+        Some(secs) => format!("after {}s", secs),
+        None => String::from("never"),
+    };
+    ctx.say(format!(
+        "delete commands: {}\ndelete replies: {}\npurge chatter: {}",
+        describe(settings.delete_commands_after),
+        describe(settings.delete_replies_after),
+        settings.purge_chatter
+    ))
+    .await?;
+    Ok(())
+}
+
+pub struct BindSettings {
+    pub delete_commands_after: Option<u64>,
+    pub delete_replies_after: Option<u64>,
+    pub purge_chatter: bool,
+}
+
+/// configure who can change the queue and how many listeners have to vote to skip, shows the
+/// settings without arguments
+#[poise::command(
+    slash_command,
+    category = "server management",
+    check = "guild_only",
+    required_permissions = "ADMINISTRATOR",
+    ephemeral
+)]
+pub(crate) async fn dj_settings(
+    ctx: Context<'_>,
+    #[description = "only this role can clear, stop or reorder the queue"] role: Option<
+        serenity::Role,
+    >,
+    #[description = "remove the DJ role, everyone is a DJ again"] remove_role: Option<bool>,
+    #[description = "percentage of listeners that have to vote to skip"] skip_percent: Option<u8>,
+) -> Result<(), Error> {
+    ctx.defer_ephemeral().await?;
+    let guild_id = ctx.guild_id().ok_or_else(|| Error::Input(NOT_IN_GUILD))?.0 as i64;
+    let database = &ctx.data().database;
+    let mut settings = get_dj_settings(database, guild_id).await?;
+    if let Some(role) = role {
+        settings.role_id = Some(role.id);
+    }
+    if remove_role == Some(true) {
+        settings.role_id = None;
+    }
+    if let Some(percent) = skip_percent {
+        if percent == 0 || percent > 100 {
+            return Err(Error::Input(INVALID_SKIP_PERCENT));
+        }
+        settings.skip_percent = percent;
+    }
+    set_dj_settings(database, guild_id, &settings).await?;
+
+    ctx.say(format!(
+        "DJ role: {}
+votes to skip: {}% of the listeners",
+        settings.role_id.map_or_else(
+            || String::from("none, everyone is a DJ"),
+            |id| format!("<@&{}>", id)
+        ),
+        settings.skip_percent
+    ))
+    .await?;
+    Ok(())
+}
+
+pub struct DjSettings {
+    pub role_id: Option<serenity::RoleId>,
+    pub skip_percent: u8,
+}
+
+impl Default for DjSettings {
+    fn default() -> Self {
+        Self {
+            role_id: None,
+            skip_percent: 50,
+        }
+    }
+}
+
+/// applies the cleanup settings of the bound channel to a new message
+pub async fn cleanup_bound_channel(
+    ctx: &serenity::Context,
+    data: &Data,
+    msg: &serenity::Message,
+) -> Result<(), Error> {
+    let guild_id = match msg.guild_id {
+        Some(guild_id) => guild_id.0 as i64,
+        None => return Ok(()),
+    };
+    if get_bound_channel_id(&data.database, guild_id).await? != Some(msg.channel_id.0) {
+        return Ok(());
+    }
+    let settings = match get_bind_settings(&data.database, guild_id).await? {
+        Some(settings) => settings,
+        None => return Ok(()),
+    };
+
+    let delete_after = if msg.author.id == ctx.cache.current_user_id() {
+        settings.delete_replies_after
+    } else if is_command(ctx, data, msg).await? {
+        settings.delete_commands_after
+    } else if settings.purge_chatter {
+        Some(0)
+    } else {
+        None
+    };
+    if let Some(secs) = delete_after {
+        let delete_at = Instant::now() + Duration::from_secs(secs);
+        let mut queues = CLEANUP_QUEUES.lock().await;
+        let queue = queues.entry(msg.channel_id).or_insert_with(|| {
+            let (queue, receiver) = mpsc::unbounded_channel();
+            tokio::spawn(run_cleanup_queue(
+                ctx.http.clone(),
+                data.database.clone(),
+                guild_id,
+                msg.channel_id,
+                receiver,
+            ));
+            queue
+        });
+        if queue.send((delete_at, msg.id)).is_err() {
+            error!("cleanup queue of channel {} is closed", msg.channel_id);
+        }
+    }
+    Ok(())
+}
+
+/// deletes the queued messages of a channel when they are due, messages that are due at the
+/// same time get deleted together
+async fn run_cleanup_queue(
+    http: Arc<serenity::Http>,
+    database: sqlx::SqlitePool,
+    guild_id: i64,
+    channel_id: serenity::ChannelId,
+    mut receiver: mpsc::UnboundedReceiver<(Instant, serenity::MessageId)>,
+) {
+    let mut pending = BinaryHeap::new();
+    loop {
+        let next = pending.peek().map(|Reverse((delete_at, _))| *delete_at);
+        let due = async {
+            match next {
+                Some(delete_at) => tokio::time::sleep_until(delete_at).await,
+                None => std::future::pending().await,
+            }
+        };
+        tokio::select! {
+            received = receiver.recv() => match received {
+                Some(entry) => pending.push(Reverse(entry)),
+                None => break,
+            },
+            _ = due => {
+                let now = Instant::now();
+                let mut msg_ids = vec![];
+                while pending.peek().map_or(false, |Reverse((delete_at, _))| *delete_at <= now) {
+                    if let Some(Reverse((_, msg_id))) = pending.pop() {
+                        msg_ids.push(msg_id);
+                    }
+                }
+                delete_messages(&http, &database, guild_id, channel_id, msg_ids).await;
+            }
+        }
+    }
+}
+
+async fn delete_messages(
+    http: &serenity::Http,
+    database: &sqlx::SqlitePool,
+    guild_id: i64,
+    channel_id: serenity::ChannelId,
+    msg_ids: Vec<serenity::MessageId>,
+) {
+    let mut delete = vec![];
+    for msg_id in msg_ids {
+        // the status message gets registered after it was sent, so check it when it's due
+        match should_be_deleted(database, guild_id, msg_id.0 as i64).await {
+            Ok(false) => delete.push(msg_id),
+            Ok(true) => {}
+            Err(why) => error!("couldn't check status message: {:?}", why),
+        }
+    }
+    for chunk in delete.chunks(BULK_DELETE_LIMIT) {
+        // bulk deletion needs at least two messages and fails if one of them is gone already,
+        // then they get deleted one by one
+        if chunk.len() > 1 && channel_id.delete_messages(http, chunk).await.is_ok() {
+            continue;
+        }
+        for msg_id in chunk {
+            // could've been deleted by someone else already
+            let _ = channel_id.delete_message(http, *msg_id).await;
+        }
+    }
+}
+
+async fn is_command(
+    ctx: &serenity::Context,
+    data: &Data,
+    msg: &serenity::Message,
+) -> Result<bool, Error> {
+    let bot_id = ctx.cache.current_user_id().0;
+    let mentioned = [format!("<@{}>", bot_id), format!("<@!{}>", bot_id)]
+        .iter()
+        .any(|mention| msg.content.starts_with(mention.as_str()));
+    Ok(mentioned || crate::strip_prefix(ctx, msg, data).await?.is_some())
+}
+
+#[poise::command(
+    slash_command,
+    prefix_command,
+    category = "server management",
+    check = "guild_only",
+    required_permissions = "ADMINISTRATOR",
+    subcommands("prefix_set", "prefix_reset", "prefix_show")
+)]
+pub(crate) async fn prefix(ctx: Context<'_>) -> Result<(), Error> {
+    prefix_show_inner(ctx).await
+}
+
+/// set the prefix of this server
+#[poise::command(
+    slash_command,
+    prefix_command,
+    rename = "set",
+    check = "guild_only",
+    required_permissions = "ADMINISTRATOR",
+    ephemeral
+)]
+pub(crate) async fn prefix_set(
+    ctx: Context<'_>,
+    #[description = "the new prefix"] prefix: String,
+) -> Result<(), Error> {
+    ctx.defer_ephemeral().await?;
+    let guild_id = ctx.guild_id().ok_or_else(|| Error::Input(NOT_IN_GUILD))?;
+    check_prefix(&prefix)?;
+    set_guild_prefix(ctx.data(), guild_id, &prefix).await?;
+    ctx.say(format!("prefix is now `{}`", prefix)).await?;
+    Ok(())
+}
+
+/// reset the prefix of this server to the default prefix
+#[poise::command(
+    slash_command,
+    prefix_command,
+    rename = "reset",
+    check = "guild_only",
+    required_permissions = "ADMINISTRATOR",
+    ephemeral
+)]
+pub(crate) async fn prefix_reset(ctx: Context<'_>) -> Result<(), Error> {
+    ctx.defer_ephemeral().await?;
+    let guild_id = ctx.guild_id().ok_or_else(|| Error::Input(NOT_IN_GUILD))?;
+    reset_guild_prefix(ctx.data(), guild_id).await?;
+    ctx.say(format!(
+        "prefix is now the default prefix `{}`",
+        ctx.data().config.prefix()
+    ))
+    .await?;
+    Ok(())
+}
+
+/// show the prefix of this server
+#[poise::command(
+    slash_command,
+    prefix_command,
+    rename = "show",
+    check = "guild_only",
+    required_permissions = "ADMINISTRATOR",
+    ephemeral
+)]
+pub(crate) async fn prefix_show(ctx: Context<'_>) -> Result<(), Error> {
+    prefix_show_inner(ctx).await
+}
+
+async fn prefix_show_inner(ctx: Context<'_>) -> Result<(), Error> {
+    ctx.defer_ephemeral().await?;
+    let guild_id = ctx.guild_id().ok_or_else(|| Error::Input(NOT_IN_GUILD))?;
+    match get_guild_prefix(ctx.data(), guild_id).await? {
+        Some(prefix) => ctx.say(format!("the prefix is `{}`", prefix)).await?,
+        None => {
+            ctx.say(format!(
+                "the prefix is the default prefix `{}`",
+                ctx.data().config.prefix()
+            ))
+            .await?
+        }
+    };
+    Ok(())
+}
+
+/// manage your personal prefixes, they work in every server
+#[poise::command(
+    slash_command,
+    prefix_command,
+    category = "General",
+    subcommands("myprefix_add", "myprefix_remove", "myprefix_list")
+)]
+pub(crate) async fn myprefix(ctx: Context<'_>) -> Result<(), Error> {
+    myprefix_list_inner(ctx).await
+}
+
+/// add a personal prefix, every prefix can only be owned by one user
+#[poise::command(slash_command, prefix_command, rename = "add", ephemeral)]
+pub(crate) async fn myprefix_add(
+    ctx: Context<'_>,
+    #[description = "the new prefix"] prefix: String,
+) -> Result<(), Error> {
+    ctx.defer_ephemeral().await?;
+    check_prefix(&prefix)?;
+    if &prefix == ctx.data().config.prefix() {
+        return Err(Error::Input(PREFIX_IS_DEFAULT));
+    }
+    let user_id = ctx.author().id;
+    if let Some(owner) = get_prefix_owner(&ctx.data().database, &prefix).await? {
+        return Err(Error::Input(if owner == user_id {
+            PREFIX_ALREADY_ADDED
+        } else {
+            PREFIX_TAKEN
+        }));
+    }
+    if get_user_prefixes(ctx.data(), user_id).await?.len() >= MAX_USER_PREFIXES {
+        return Err(Error::Input(TOO_MANY_PREFIXES));
+    }
+    add_user_prefix(ctx.data(), user_id, &prefix).await?;
+    ctx.say(format!("added personal prefix `{}`", prefix))
+        .await?;
+    Ok(())
+}
+
+/// remove a personal prefix
+#[poise::command(slash_command, prefix_command, rename = "remove", ephemeral)]
+pub(crate) async fn myprefix_remove(
+    ctx: Context<'_>,
+    #[description = "the prefix to remove"] prefix: String,
+) -> Result<(), Error> {
+    ctx.defer_ephemeral().await?;
+    let user_id = ctx.author().id;
+    if !get_user_prefixes(ctx.data(), user_id)
+        .await?
+        .contains(&prefix)
+    {
+        return Err(Error::Input(UNKNOWN_PREFIX));
+    }
+    remove_user_prefix(ctx.data(), user_id, &prefix).await?;
+    ctx.say(format!("removed personal prefix `{}`", prefix))
+        .await?;
+    Ok(())
+}
+
+/// list your personal prefixes
+#[poise::command(slash_command, prefix_command, rename = "list", ephemeral)]
+pub(crate) async fn myprefix_list(ctx: Context<'_>) -> Result<(), Error> {
+    myprefix_list_inner(ctx).await
+}
+
+async fn myprefix_list_inner(ctx: Context<'_>) -> Result<(), Error> {
+    ctx.defer_ephemeral().await?;
+    let prefixes = get_user_prefixes(ctx.data(), ctx.author().id).await?;
+    if prefixes.is_empty() {
+        ctx.say("you don't have any personal prefixes").await?;
+    } else {
+        ctx.say(format!(
+            "your personal prefixes ({}/{}): {}",
+            prefixes.len(),
+            MAX_USER_PREFIXES,
+            prefixes
+                .iter()
+                .map(|prefix| format!("`{}`", prefix))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ))
+        .await?;
+    }
+    Ok(())
+}
+
+fn check_prefix(prefix: &str) -> Result<(), Error> {
+    if prefix.is_empty()
+        || prefix.chars().count() > MAX_PREFIX_LEN
+        || prefix.chars().any(char::is_whitespace)
+    {
+        Err(Error::Input(INVALID_PREFIX))
+    } else {
+        Ok(())
+    }
+}
+
+/// gets the personal prefixes of the user, longest prefix first
+pub async fn get_user_prefixes(
+    data: &Data,
+    user_id: serenity::UserId,
+) -> Result<Vec<String>, Error> {
+    if let Some(prefixes) = data.user_prefixes.read().await.get(&user_id) {
+        return Ok(prefixes.clone());
+    }
+    let id = user_id.0 as i64;
+    let mut prefixes = sqlx::query!("SELECT string FROM user_prefix WHERE user_id = ?", id)
+        .fetch_all(&data.database)
+        .await?
+        .into_iter()
+        .map(|entry| entry.string)
+        .collect::<Vec<_>>();
+    // longer prefixes have to be checked first, otherwise `!` would shadow `!!`
+    prefixes.sort_by(|a, b| b.len().cmp(&a.len()));
+    data.user_prefixes
+        .write()
+        .await
+        .insert(user_id, prefixes.clone());
+    Ok(prefixes)
+}
+
+pub async fn get_prefix_owner(
+    database: &sqlx::SqlitePool,
+    prefix: &str,
+) -> Result<Option<serenity::UserId>, Error> {
+    Ok(
+        sqlx::query!("SELECT user_id FROM user_prefix WHERE string = ?", prefix)
+            .fetch_optional(database)
+            .await?
+            .map(|entry| serenity::UserId(entry.user_id as u64)),
+    )
+}
+
+pub async fn add_user_prefix(
+    data: &Data,
+    user_id: serenity::UserId,
+    prefix: &str,
+) -> Result<(), Error> {
+    let id = user_id.0 as i64;
+    sqlx::query!(
+        "INSERT INTO user_prefix (string, user_id) VALUES (?, ?)",
+        prefix,
+        id
+    )
+    .execute(&data.database)
+    .await
+    // someone else could've added it since the owner was checked
+    .map_err(|why| {
+        if is_unique_violation(&why) {
+            Error::Input(PREFIX_TAKEN)
+        } else {
+            why.into()
+        }
+    })?;
+    data.user_prefixes.write().await.remove(&user_id);
+    Ok(())
+}
+
+pub async fn remove_user_prefix(
+    data: &Data,
+    user_id: serenity::UserId,
+    prefix: &str,
+) -> Result<(), Error> {
+    let id = user_id.0 as i64;
+    sqlx::query!(
+        "DELETE FROM user_prefix WHERE string = ? AND user_id = ?",
+        prefix,
+        id
+    )
+    .execute(&data.database)
+    .await?;
+    data.user_prefixes.write().await.remove(&user_id);
+    Ok(())
+}
+
+/// gets the prefix of the guild, `None` if the guild uses the default prefix
+pub async fn get_guild_prefix(
+    data: &Data,
+    guild_id: serenity::GuildId,
+) -> Result<Option<String>, Error> {
+    if let Some(prefix) = data.guild_prefixes.read().await.get(&guild_id) {
+        return Ok(prefix.clone());
+    }
+    let id = guild_id.0 as i64;
+    let prefix = sqlx::query!("SELECT prefix FROM guild WHERE guild_id = ?", id)
+        .fetch_optional(&data.database)
+        .await?
+        .map(|entry| entry.prefix);
+    data.guild_prefixes
+        .write()
+        .await
+        .insert(guild_id, prefix.clone());
+    Ok(prefix)
+}
+
+pub async fn set_guild_prefix(
+    data: &Data,
+    guild_id: serenity::GuildId,
+    prefix: &str,
+) -> Result<(), Error> {
+    let id = guild_id.0 as i64;
+    sqlx::query!(
+        "INSERT OR IGNORE INTO guild (guild_id, prefix) VALUES (?, ?)",
+        id,
+        prefix,
+    )
+    .execute(&data.database)
+    .await?;
+    sqlx::query!("UPDATE guild SET prefix = ? WHERE guild_id = ?", prefix, id)
+        .execute(&data.database)
+        .await?;
+    data.guild_prefixes.write().await.remove(&guild_id);
+    Ok(())
+}
+
+pub async fn reset_guild_prefix(data: &Data, guild_id: serenity::GuildId) -> Result<(), Error> {
+    let id = guild_id.0 as i64;
+    sqlx::query!("DELETE FROM guild WHERE guild_id = ?", id)
+        .execute(&data.database)
+        .await?;
+    data.guild_prefixes.write().await.remove(&guild_id);
+    Ok(())
+}
+
 pub async fn get_bound_channel_id(
     database: &sqlx::SqlitePool,
     guild_id: i64,
@@ -85,7 +656,6 @@ pub async fn get_bound_channel_id(
     .map(|entry| entry.bind_id as u64))
 }
 
-#[allow(dead_code)]
 pub async fn should_be_deleted(
     database: &sqlx::SqlitePool,
     guild_id: i64,
@@ -101,7 +671,6 @@ pub async fn should_be_deleted(
     .any(|entry| entry.msg_id == msg))
 }
 
-#[allow(dead_code)]
 pub async fn unregister_msg(
     database: &sqlx::SqlitePool,
     guild_id: i64,
@@ -117,7 +686,6 @@ pub async fn unregister_msg(
     Ok(())
 }
 
-#[allow(dead_code)]
 pub async fn register_msg(
     database: &sqlx::SqlitePool,
     guild_id: i64,
@@ -133,6 +701,76 @@ pub async fn register_msg(
     Ok(())
 }
 
+pub async fn get_bind_settings(
+    database: &sqlx::SqlitePool,
+    guild_id: i64,
+) -> Result<Option<BindSettings>, Error> {
+    Ok(sqlx::query!(
+        "SELECT delete_commands_after, delete_replies_after, purge_chatter FROM guild_bind WHERE guild_id = ?",
+        guild_id
+    )
+    .fetch_optional(database)
+    .await?
+    .map(|entry| BindSettings {
+        delete_commands_after: entry.delete_commands_after.map(|secs| secs as u64),
+        delete_replies_after: entry.delete_replies_after.map(|secs| secs as u64),
+        purge_chatter: entry.purge_chatter,
+    }))
+}
+
+pub async fn set_bind_settings(
+    database: &sqlx::SqlitePool,
+    guild_id: i64,
+    settings: &BindSettings,
+) -> Result<(), Error> {
+    let delete_commands_after = settings.delete_commands_after.map(|secs| secs as i64);
+    let delete_replies_after = settings.delete_replies_after.map(|secs| secs as i64);
+    sqlx::query!(
+        "UPDATE guild_bind SET delete_commands_after = ?, delete_replies_after = ?, purge_chatter = ? WHERE guild_id = ?",
+        delete_commands_after,
+        delete_replies_after,
+        settings.purge_chatter,
+        guild_id
+    )
+    .execute(database)
+    .await?;
+    Ok(())
+}
+
+pub async fn get_dj_settings(
+    database: &sqlx::SqlitePool,
+    guild_id: i64,
+) -> Result<DjSettings, Error> {
+    Ok(sqlx::query!(
+        "SELECT role_id, skip_percent FROM guild_dj WHERE guild_id = ?",
+        guild_id
+    )
+    .fetch_optional(database)
+    .await?
+    .map_or_else(DjSettings::default, |entry| DjSettings {
+        role_id: entry.role_id.map(|id| serenity::RoleId(id as u64)),
+        skip_percent: entry.skip_percent as u8,
+    }))
+}
+
+pub async fn set_dj_settings(
+    database: &sqlx::SqlitePool,
+    guild_id: i64,
+    settings: &DjSettings,
+) -> Result<(), Error> {
+    let role_id = settings.role_id.map(|id| id.0 as i64);
+    let skip_percent = settings.skip_percent as i64;
+    sqlx::query!(
+        "INSERT OR REPLACE INTO guild_dj (guild_id, role_id, skip_percent) VALUES (?, ?, ?)",
+        guild_id,
+        role_id,
+        skip_percent
+    )
+    .execute(database)
+    .await?;
+    Ok(())
+}
+
 pub async fn bind_channel(
     database: &sqlx::SqlitePool,
     guild_id: i64,
@@ -163,7 +801,6 @@ pub async fn unbind_channel(database: &sqlx::SqlitePool, guild_id: i64) -> Resul
     Ok(())
 }
 
-#[allow(dead_code)]
 pub async fn get_status_msg(
     database: &sqlx::SqlitePool,
     guild_id: i64,
@@ -176,3 +813,12 @@ pub async fn get_status_msg(
     .await?
     .map(|entry| entry.msg_id as u64))
 }
+
+pub async fn get_status_msgs(database: &sqlx::SqlitePool) -> Result<Vec<(i64, i64)>, Error> {
+    Ok(sqlx::query!("SELECT guild_id, msg_id FROM update_message")
+        .fetch_all(database)
+        .await?
+        .iter()
+        .map(|entry| (entry.guild_id, entry.msg_id))
+        .collect())
+}
@@ -0,0 +1,745 @@
+use itertools::Itertools;
+use mensa_swfr_rs::mensa::{self as swfr, Day};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use std::{collections::HashMap, str::FromStr};
+use strum_macros::{Display, EnumString};
+use tracing::error;
+
+use crate::mensa::{
+    self, find_location, location_by_id, CachedPlan, Location, DEFAULT_LOCATION, LOCATIONS,
+};
+use crate::{
+    utils::{check_result_ayame, guild_only, translate_weekday, weekday_german},
+    Context, Data, Error,
+};
+use chrono::{Datelike, NaiveTime, Weekday};
+use poise::serenity_prelude::{self as serenity, CreateEmbed, CreateSelectMenuOptions};
+
+use crate::error::*;
+
+// notes like allergens are separated from the dish by dashes
+static NOTES_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"--+").expect("invalid notes regex"));
+// allergen and additive codes, e.g. `Gl`, `Sel` or `3`
+static ALLERGEN_CODE_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^(?:[0-9]{1,2}|[A-Z][a-z]{1,2})$").expect("invalid allergen regex"));
+// the codes are listed in brackets after the dish, e.g. `(Gl,Ei,3)`
+static BRACKETS_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"[(\[]([^()\[\]]*)[)\]]").expect("invalid brackets regex"));
+const MENSA_SCHEDULE_INTERVAL: std::time::Duration = std::time::Duration::from_secs(60);
+
+#[derive(Display, EnumString, Clone, Copy, PartialEq)]
+#[strum(serialize_all = "lowercase")]
+pub enum PriceTier {
+    Student,
+    Worker,
+    Guest,
+}
+
+/// what a user wants to eat and which price they pay
+pub struct MensaPreferences {
+    pub vegan: bool,
+    pub vegetarian: bool,
+    pub no_pork: bool,
+    pub allergens: Vec<String>,
+    pub price_tier: PriceTier,
+    pub hide_filtered: bool,
+    pub location: Option<u32>,
+}
+
+impl Default for MensaPreferences {
+    fn default() -> Self {
+        Self {
+            vegan: false,
+            vegetarian: false,
+            no_pork: false,
+            allergens: vec![],
+            price_tier: PriceTier::Student,
+            hide_filtered: false,
+            location: None,
+        }
+    }
+}
+
+impl MensaPreferences {
+    fn has_filters(&self) -> bool {
+        self.vegan || self.vegetarian || self.no_pork || !self.allergens.is_empty()
+    }
+
+    /// whether the menu fits the dietary preferences
+    pub fn matches(&self, menu: &swfr::Menu) -> bool {
+        let food_type = menu.food_type.as_deref().unwrap_or_default().to_lowercase();
+        let name = menu.name.to_lowercase();
+        let vegan = food_type.contains("vegan");
+        let vegetarian = vegan || food_type.contains("vegetarisch");
+        let pork = food_type.contains("schwein") || name.contains("schwein");
+        let allergens = menu_allergens(menu);
+        (!self.vegan || vegan)
+            && (!self.vegetarian || vegetarian)
+            && (!self.no_pork || !pork)
+            && !self
+                .allergens
+                .iter()
+                .any(|allergen| allergens.contains(allergen))
+    }
+
+    fn price(&self, price: &swfr::Price) -> String {
+        match self.price_tier {
+            PriceTier::Student => price.price_students.to_string(),
+            PriceTier::Worker => price.price_workers.to_string(),
+            PriceTier::Guest => price.price_guests.to_string(),
+        }
+    }
+}
+
+/// the lowercase allergen and additive codes of the food type and the name of the menu
+fn menu_allergens(menu: &swfr::Menu) -> Vec<String> {
+    allergen_codes(
+        menu.food_type
+            .iter()
+            .map(|food_type| food_type.as_str())
+            .chain(NOTES_REGEX.split(&menu.name)),
+    )
+}
+
+/// the codes of every list in brackets and of every text that is nothing but a code list,
+/// words of the description are never taken for codes
+fn allergen_codes<'a>(texts: impl Iterator<Item = &'a str>) -> Vec<String> {
+    let mut codes = vec![];
+    for text in texts {
+        let lists = BRACKETS_REGEX
+            .captures_iter(text)
+            .filter_map(|cap| cap.get(1))
+            .map(|list| list.as_str())
+            .chain(std::iter::once(text));
+        for list in lists {
+            let list = list.split(',').map(str::trim).collect::<Vec<_>>();
+            if list.iter().all(|code| ALLERGEN_CODE_REGEX.is_match(code)) {
+                codes.extend(list.iter().map(|code| code.to_lowercase()));
+            }
+        }
+    }
+    codes.into_iter().unique().collect()
+}
+
+/// show the mensa plan, `/mensa preferences` filters it
+#[poise::command(
+    prefix_command,
+    slash_command,
+    track_edits,
+    category = "General",
+    subcommands(
+        "mensa_show",
+        "mensa_preferences",
+        "mensa_subscribe",
+        "mensa_unsubscribe",
+        "mensa_server_location"
+    )
+)]
+pub(crate) async fn mensa(
+    ctx: Context<'_>,
+    #[description = "The day to look up"] day: Option<String>,
+    #[description = "The mensa, defaults to your or the server's location"]
+    #[autocomplete = "autocomplete_location"]
+    location: Option<String>,
+) -> Result<(), Error> {
+    mensa_show_inner(ctx, day, location).await
+}
+
+/// show the mensa plan of a day
+#[poise::command(prefix_command, slash_command, track_edits, rename = "show")]
+pub(crate) async fn mensa_show(
+    ctx: Context<'_>,
+    #[description = "The day to look up"] day: Option<String>,
+    #[description = "The mensa, defaults to your or the server's location"]
+    #[autocomplete = "autocomplete_location"]
+    location: Option<String>,
+) -> Result<(), Error> {
+    mensa_show_inner(ctx, day, location).await
+}
+
+async fn mensa_show_inner(
+    ctx: Context<'_>,
+    day: Option<String>,
+    location: Option<String>,
+) -> Result<(), Error> {
+    ctx.defer_or_broadcast().await?;
+    let config = &ctx.data().config;
+    let mensa_key = config.mensa_api_key();
+    if let Some(mensa_key) = mensa_key {
+        let preferences =
+            get_mensa_preferences(&ctx.data().database, ctx.author().id.0 as i64).await?;
+        let location = match location {
+            Some(location) => {
+                find_location(&location).ok_or_else(|| Error::Input(UNKNOWN_LOCATION))?
+            }
+            None => default_location(&ctx, &preferences).await?,
+        };
+        let cached = ctx.data().mensa_plans.get(mensa_key, location).await?;
+        let days = cached.plan.days();
+        let day = match day {
+            Some(day) => match Weekday::from_str(&translate_weekday(&day)) {
+                Ok(day) => day,
+                Err(_) => return Err(Error::Input(UNKNOWN_WEEKDAY)),
+            },
+            None => mensa::now().weekday(),
+        };
+
+        let uuid = ctx.id();
+        let embed = create_mensa_embed(&days, &day, &preferences, location, &cached);
+        let reply = ctx
+            .send(|m| {
+                m.embed(|e| {
+                    e.clone_from(&embed);
+                    e
+                })
+                .components(|c| {
+                    c.create_action_row(|ar| {
+                        ar.create_select_menu(|menu| {
+                            menu.options(|e| create_mensa_options(e, &day, &days))
+                                .custom_id(uuid)
+                        })
+                    })
+                })
+            })
+            .await?;
+
+        while let Some(mci) = serenity::CollectComponentInteraction::new(ctx.discord())
+            .author_id(ctx.author().id)
+            .channel_id(ctx.channel_id())
+            .timeout(std::time::Duration::from_secs(120))
+            .filter(move |mci| mci.data.custom_id == uuid.to_string())
+            .await
+        {
+            let dds = &mci.data.values;
+            if let Some(day) = dds.get(0) {
+                let day = match Weekday::from_str(&translate_weekday(&day)) {
+                    Ok(day) => day,
+                    Err(_) => return Err(Error::Input(UNKNOWN_WEEKDAY)),
+                };
+
+                let embed = create_mensa_embed(&days, &day, &preferences, location, &cached);
+                let mut msg = mci.message.clone();
+                msg.edit(ctx.discord(), |m| {
+                    m.set_embed(embed).components(|c| {
+                        c.create_action_row(|ar| {
+                            ar.create_select_menu(|menu| {
+                                menu.options(|e| create_mensa_options(e, &day, &days))
+                                    .custom_id(uuid)
+                            })
+                        })
+                    })
+                })
+                .await?;
+
+                mci.create_interaction_response(ctx.discord(), |ir| {
+                    ir.kind(serenity::InteractionResponseType::DeferredUpdateMessage)
+                })
+                .await?;
+            }
+        }
+        // delete message, but ignore result because we dont want the interaction to fail
+        let _ = reply.message().await?.delete(&ctx.discord().http).await;
+        Ok(())
+    } else {
+        Err(Error::Failure(NO_MENSA_KEY))
+    }
+}
+
+/// set which menus get highlighted and which price gets shown, shows them without arguments
+#[poise::command(prefix_command, slash_command, rename = "preferences", ephemeral)]
+pub(crate) async fn mensa_preferences(
+    ctx: Context<'_>,
+    #[description = "only vegan menus"] vegan: Option<bool>,
+    #[description = "only vegetarian menus"] vegetarian: Option<bool>,
+    #[description = "no menus with pork"] no_pork: Option<bool>,
+    #[description = "allergen codes to avoid separated by commas, `-` clears them"]
+    allergens: Option<String>,
+    #[description = "student, worker or guest"] price: Option<String>,
+    #[description = "hide menus that don't match instead of highlighting the matching ones"]
+    hide: Option<bool>,
+    #[description = "your default mensa, `-` uses the server's location"]
+    #[autocomplete = "autocomplete_location"]
+    location: Option<String>,
+) -> Result<(), Error> {
+    ctx.defer_ephemeral().await?;
+    let database = &ctx.data().database;
+    let user_id = ctx.author().id.0 as i64;
+    let mut preferences = get_mensa_preferences(database, user_id).await?;
+    if let Some(vegan) = vegan {
+        preferences.vegan = vegan;
+    }
+    if let Some(vegetarian) = vegetarian {
+        preferences.vegetarian = vegetarian;
+    }
+    if let Some(no_pork) = no_pork {
+        preferences.no_pork = no_pork;
+    }
+    if let Some(allergens) = allergens {
+        preferences.allergens = parse_allergens(&allergens);
+    }
+    if let Some(price) = price {
+        preferences.price_tier = PriceTier::from_str(&price.to_lowercase())
+            .map_err(|_| Error::Input(UNKNOWN_PRICE_TIER))?;
+    }
+    if let Some(hide) = hide {
+        preferences.hide_filtered = hide;
+    }
+    if let Some(location) = location {
+        preferences.location = match location.trim() {
+            "-" => None,
+            location => Some(
+                find_location(location)
+                    .ok_or_else(|| Error::Input(UNKNOWN_LOCATION))?
+                    .id,
+            ),
+        };
+    }
+    set_mensa_preferences(database, user_id, &preferences).await?;
+
+    ctx.say(format!(
+        "vegan: {}\nvegetarian: {}\nno pork: {}\nallergens: {}\nprice: {}\nhide other menus: {}\nlocation: {}",
+        preferences.vegan,
+        preferences.vegetarian,
+        preferences.no_pork,
+        if preferences.allergens.is_empty() {
+            String::from("none")
+        } else {
+            preferences.allergens.join(", ")
+        },
+        preferences.price_tier,
+        preferences.hide_filtered,
+        preferences
+            .location
+            .and_then(location_by_id)
+            .map_or("server default", |location| location.name)
+    ))
+    .await?;
+    Ok(())
+}
+
+/// post the plan of the day into a channel on every weekday
+#[poise::command(
+    prefix_command,
+    slash_command,
+    rename = "subscribe",
+    check = "guild_only",
+    required_permissions = "ADMINISTRATOR",
+    ephemeral
+)]
+pub(crate) async fn mensa_subscribe(
+    ctx: Context<'_>,
+    #[description = "the channel that gets the plan"] channel: serenity::Channel,
+    #[description = "the time of the post, e.g. 10:30"] time: String,
+    #[description = "post \"Keine Mensa\" on days the mensa is closed"] post_closed: Option<bool>,
+    #[description = "the mensa, defaults to the server's location"]
+    #[autocomplete = "autocomplete_location"]
+    location: Option<String>,
+) -> Result<(), Error> {
+    ctx.defer_ephemeral().await?;
+    if ctx.data().config.mensa_api_key().is_none() {
+        return Err(Error::Failure(NO_MENSA_KEY));
+    }
+    let guild_id = ctx.guild_id().ok_or_else(|| Error::Input(NOT_IN_GUILD))?.0 as i64;
+    let channel = match channel.guild() {
+        Some(channel) if channel.kind == serenity::ChannelType::Text => channel,
+        _ => return Err(Error::Input(WRONG_CHANNEL_TO_BIND)),
+    };
+    let time =
+        NaiveTime::parse_from_str(time.trim(), "%H:%M").map_err(|_| Error::Input(INVALID_TIME))?;
+    let post_time = time.format("%H:%M").to_string();
+    let channel_id = channel.id.0 as i64;
+    let post_closed = post_closed.unwrap_or(false);
+    let location = match location {
+        Some(location) => Some(
+            find_location(&location)
+                .ok_or_else(|| Error::Input(UNKNOWN_LOCATION))?
+                .id as i64,
+        ),
+        None => None,
+    };
+    // don't post today anymore if the time already passed
+    let now = mensa::now();
+    let last_posted = if now.time() >= time {
+        Some(now.format("%Y-%m-%d").to_string())
+    } else {
+        None
+    };
+    sqlx::query!(
+        "INSERT OR REPLACE INTO mensa_subscription (guild_id, channel_id, post_time, post_closed, last_posted, location) VALUES (?, ?, ?, ?, ?, ?)",
+        guild_id,
+        channel_id,
+        post_time,
+        post_closed,
+        last_posted,
+        location
+    )
+    .execute(&ctx.data().database)
+    .await?;
+    ctx.say(format!(
+        "the plan gets posted in <#{}> at {} on weekdays",
+        channel_id, post_time
+    ))
+    .await?;
+    Ok(())
+}
+
+/// stop posting the plan of the day
+#[poise::command(
+    prefix_command,
+    slash_command,
+    rename = "unsubscribe",
+    check = "guild_only",
+    required_permissions = "ADMINISTRATOR",
+    ephemeral
+)]
+pub(crate) async fn mensa_unsubscribe(ctx: Context<'_>) -> Result<(), Error> {
+    ctx.defer_ephemeral().await?;
+    let guild_id = ctx.guild_id().ok_or_else(|| Error::Input(NOT_IN_GUILD))?.0 as i64;
+    let deleted = sqlx::query!(
+        "DELETE FROM mensa_subscription WHERE guild_id = ?",
+        guild_id
+    )
+    .execute(&ctx.data().database)
+    .await?
+    .rows_affected();
+    if deleted == 0 {
+        return Err(Error::Input(NO_MENSA_SUBSCRIPTION));
+    }
+    ctx.say("the plan doesn't get posted anymore").await?;
+    Ok(())
+}
+
+/// checks every minute if a subscribed plan has to be posted
+pub fn start_mensa_scheduler(ctx: &serenity::Context, data: &Data) {
+    let (ctx, data) = (ctx.clone(), data.clone());
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(MENSA_SCHEDULE_INTERVAL);
+        loop {
+            interval.tick().await;
+            check_result_ayame(post_scheduled_plans(&ctx, &data).await);
+        }
+    });
+}
+
+async fn post_scheduled_plans(ctx: &serenity::Context, data: &Data) -> Result<(), Error> {
+    let now = mensa::now();
+    let weekday = now.weekday();
+    if weekday == Weekday::Sat || weekday == Weekday::Sun {
+        return Ok(());
+    }
+    let today = now.format("%Y-%m-%d").to_string();
+    let time = now.format("%H:%M").to_string();
+    // times are zero padded, so they can be compared as text
+    let due = sqlx::query!(
+        "SELECT mensa_subscription.guild_id, channel_id, post_closed, mensa_subscription.location AS location, guild_mensa_location.location AS \"guild_location?\" FROM mensa_subscription LEFT JOIN guild_mensa_location ON mensa_subscription.guild_id = guild_mensa_location.guild_id WHERE post_time <= ? AND (last_posted IS NULL OR last_posted < ?)",
+        time,
+        today
+    )
+    .fetch_all(&data.database)
+    .await?;
+    if due.is_empty() {
+        return Ok(());
+    }
+    let mensa_key = data
+        .config
+        .mensa_api_key()
+        .as_ref()
+        .ok_or_else(|| Error::Failure(NO_MENSA_KEY))?;
+
+    for subscription in due {
+        let location = subscription
+            .location
+            .or(subscription.guild_location)
+            .and_then(|id| location_by_id(id as u32))
+            .unwrap_or(DEFAULT_LOCATION);
+        // if the request fails the plan gets posted with the next try
+        let cached = match data.mensa_plans.get(mensa_key, location).await {
+            Ok(cached) => cached,
+            Err(why) => {
+                error!("couldn't get mensa plan of {}: {:?}", location.name, why);
+                continue;
+            }
+        };
+        let days = cached.plan.days();
+        let open = days
+            .get(&weekday)
+            .map_or(false, |day| !day.menues.is_empty());
+        let embed = create_mensa_embed(
+            &days,
+            &weekday,
+            &MensaPreferences::default(),
+            location,
+            &cached,
+        );
+        if open || subscription.post_closed {
+            let channel = serenity::ChannelId(subscription.channel_id as u64);
+            if let Err(why) = channel
+                .send_message(&ctx.http, |m| m.set_embed(embed.clone()))
+                .await
+            {
+                error!("couldn't post mensa plan in {}: {:?}", channel, why);
+            }
+        }
+        sqlx::query!(
+            "UPDATE mensa_subscription SET last_posted = ? WHERE guild_id = ?",
+            today,
+            subscription.guild_id
+        )
+        .execute(&data.database)
+        .await?;
+    }
+    Ok(())
+}
+
+/// set the mensa of this server, users can pick their own with `/mensa preferences`
+#[poise::command(
+    prefix_command,
+    slash_command,
+    rename = "serverlocation",
+    check = "guild_only",
+    required_permissions = "ADMINISTRATOR",
+    ephemeral
+)]
+pub(crate) async fn mensa_server_location(
+    ctx: Context<'_>,
+    #[description = "the mensa"]
+    #[autocomplete = "autocomplete_location"]
+    location: String,
+) -> Result<(), Error> {
+    ctx.defer_ephemeral().await?;
+    let guild_id = ctx.guild_id().ok_or_else(|| Error::Input(NOT_IN_GUILD))?.0 as i64;
+    let location = find_location(&location).ok_or_else(|| Error::Input(UNKNOWN_LOCATION))?;
+    let location_id = location.id as i64;
+    sqlx::query!(
+        "INSERT OR REPLACE INTO guild_mensa_location (guild_id, location) VALUES (?, ?)",
+        guild_id,
+        location_id
+    )
+    .execute(&ctx.data().database)
+    .await?;
+    ctx.say(format!("the mensa of this server is now {}", location.name))
+        .await?;
+    Ok(())
+}
+
+/// the location of the user, else the location of the guild
+async fn default_location(
+    ctx: &Context<'_>,
+    preferences: &MensaPreferences,
+) -> Result<&'static Location, Error> {
+    if let Some(location) = preferences.location.and_then(location_by_id) {
+        return Ok(location);
+    }
+    let guild_id = match ctx.guild_id() {
+        Some(guild_id) => guild_id.0 as i64,
+        None => return Ok(DEFAULT_LOCATION),
+    };
+    Ok(sqlx::query!(
+        "SELECT location FROM guild_mensa_location WHERE guild_id = ?",
+        guild_id
+    )
+    .fetch_optional(&ctx.data().database)
+    .await?
+    .and_then(|entry| location_by_id(entry.location as u32))
+    .unwrap_or(DEFAULT_LOCATION))
+}
+
+async fn autocomplete_location(
+    _ctx: Context<'_>,
+    partial: String,
+) -> impl Iterator<Item = poise::AutocompleteChoice<String>> {
+    let partial = partial.to_lowercase();
+    LOCATIONS
+        .iter()
+        .filter(move |location| location.name.to_lowercase().contains(&partial))
+        .map(|location| poise::AutocompleteChoice {
+            name: location.name.to_string(),
+            value: location.name.to_string(),
+        })
+}
+
+fn parse_allergens(allergens: &str) -> Vec<String> {
+    allergens
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .map(|code| code.trim().to_lowercase())
+        .filter(|code| !code.is_empty() && code != "-")
+        .unique()
+        .collect()
+}
+
+fn create_mensa_options<'a>(
+    opt: &'a mut CreateSelectMenuOptions,
+    day: &Weekday,
+    days: &HashMap<Weekday, &Day>,
+) -> &'a mut CreateSelectMenuOptions {
+    for d in days
+        .keys()
+        .map(|w| *w)
+        .sorted_by(|a, b| Ord::cmp(&(*a as u8), &(*b as u8)))
+    {
+        opt.create_option(|p| {
+            p.label(format!("{}", weekday_german(&d)))
+                .value(format!("{}", weekday_german(&d)));
+            if d == *day {
+                p.default_selection(true);
+            }
+            p
+        });
+    }
+
+    opt
+}
+
+fn create_mensa_embed(
+    days: &HashMap<Weekday, &Day>,
+    day: &Weekday,
+    preferences: &MensaPreferences,
+    location: &Location,
+    cached: &CachedPlan,
+) -> CreateEmbed {
+    let (mut embed, hidden) = create_day_embed(days, day, preferences);
+    embed.author(|a| a.name(location.name));
+    let mut notes = vec![];
+    if hidden > 0 {
+        notes.push(format!("{} Menüs ausgeblendet", hidden));
+    }
+    if cached.stale {
+        notes.push(format!(
+            "veraltet, Stand {}",
+            cached.fetched_at.format("%d.%m. %H:%M")
+        ));
+    }
+    if !notes.is_empty() {
+        embed.footer(|f| f.text(notes.join(" · ")));
+    }
+    embed
+}
+
+/// the embed of the day with the amount of hidden menus
+fn create_day_embed(
+    days: &HashMap<Weekday, &Day>,
+    day: &Weekday,
+    preferences: &MensaPreferences,
+) -> (CreateEmbed, usize) {
+    match days.get(day) {
+        Some(day) => match create_mensa_plan_by_day(day, preferences) {
+            Ok(plan) => plan,
+            Err(_) => {
+                let mut e = CreateEmbed::default();
+                e.title("Keine Mensa für den ausgewählten Tag");
+                return (e, 0);
+            }
+        },
+        None => {
+            let mut e = CreateEmbed::default();
+            e.title("Keine Mensa für den ausgewählten Tag");
+            (e, 0)
+        }
+    }
+}
+
+/// creates the embed of the day, returns it with the amount of menus that got hidden
+pub fn create_mensa_plan_by_day(
+    day: &swfr::Day,
+    preferences: &MensaPreferences,
+) -> Result<(CreateEmbed, usize), Box<dyn std::error::Error>> {
+    let mut embed = CreateEmbed::default();
+    embed.title(format!(
+        "{} ({})",
+        weekday_german(&day.weekday()?),
+        day.to_chrono()?.format("%d.%m.%Y")
+    ));
+    let filtered = preferences.has_filters();
+    let mut hidden = 0;
+    for menu in day.menues.iter() {
+        let matches = preferences.matches(menu);
+        if filtered && preferences.hide_filtered && !matches {
+            hidden += 1;
+            continue;
+        }
+        embed.field(
+            if filtered && matches {
+                format!("✅ {}", menu.art)
+            } else {
+                menu.art.clone()
+            },
+            format!(
+                "{}\n\nZusatz: {}\n\nPreis: {}",
+                NOTES_REGEX.replace(&menu.name, "\n\n!!! "),
+                match &menu.food_type {
+                    Some(typ) => typ,
+                    None => "None",
+                },
+                preferences.price(&menu.price),
+            ),
+            false,
+        );
+    }
+    Ok((embed, hidden))
+}
+
+pub async fn get_mensa_preferences(
+    database: &sqlx::SqlitePool,
+    user_id: i64,
+) -> Result<MensaPreferences, Error> {
+    let entry = sqlx::query!(
+        "SELECT vegan, vegetarian, no_pork, allergens, price_tier, hide_filtered, location FROM mensa_preference WHERE user_id = ?",
+        user_id
+    )
+    .fetch_optional(database)
+    .await?;
+    Ok(match entry {
+        Some(entry) => MensaPreferences {
+            vegan: entry.vegan,
+            vegetarian: entry.vegetarian,
+            no_pork: entry.no_pork,
+            allergens: parse_allergens(&entry.allergens),
+            price_tier: PriceTier::from_str(&entry.price_tier).unwrap_or(PriceTier::Student),
+            hide_filtered: entry.hide_filtered,
+            location: entry.location.map(|id| id as u32),
+        },
+        None => MensaPreferences::default(),
+    })
+}
+
+pub async fn set_mensa_preferences(
+    database: &sqlx::SqlitePool,
+    user_id: i64,
+    preferences: &MensaPreferences,
+) -> Result<(), Error> {
+    let allergens = preferences.allergens.join(",");
+    let price_tier = preferences.price_tier.to_string();
+    let location = preferences.location.map(|id| id as i64);
+    sqlx::query!(
+        "INSERT OR REPLACE INTO mensa_preference (user_id, vegan, vegetarian, no_pork, allergens, price_tier, hide_filtered, location) VALUES (?, ?, ?, ?, ?, ?, ?, ?)",
+        user_id,
+        preferences.vegan,
+        preferences.vegetarian,
+        preferences.no_pork,
+        allergens,
+        price_tier,
+        preferences.hide_filtered,
+        location
+    )
+    .execute(database)
+    .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::commands::mensa::*;
+
+    #[test]
+    fn test_allergen_codes() {
+        let name = "Hähnchenbrust mit Reis und Gemüse (Gl,Ei,Sel,3)----Salat mit Dressing (Mi, 2)";
+        assert_eq!(
+            allergen_codes(NOTES_REGEX.split(name)),
+            vec!["gl", "ei", "sel", "3", "mi", "2"]
+        );
+        // the food type can be a plain list, words stay words
+        assert_eq!(allergen_codes(["Gl, So"].iter().copied()), vec!["gl", "so"]);
+        assert!(allergen_codes(["vegan", "mit Reis und Soße (scharf)"].iter().copied()).is_empty());
+    }
+}
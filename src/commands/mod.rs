@@ -1,3 +1,7 @@
+pub mod emote;
 pub mod general;
 pub mod manage;
+pub mod mensa;
+pub mod music;
 pub mod owner;
+pub mod playlist;
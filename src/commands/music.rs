@@ -0,0 +1,848 @@
+use std::{future::Future, ops::RangeBounds, pin::Pin, str::FromStr, sync::Arc, time::Duration};
+
+use itertools::Itertools;
+use poise::serenity_prelude::{self as serenity, CreateEmbed};
+use rand::seq::SliceRandom;
+
+use crate::commands::manage::get_dj_settings;
+use crate::error::*;
+use crate::menu::{Control, Cursor, Menu, MenuComponent};
+use crate::utils::{bind_command, dj_command, dj_only_command, format_duration, parse_timestamp};
+use crate::voice::{self, LoopMode, PlaylistEntry};
+use crate::youtube::{hyperlink_result, Type, YoutubeSearch};
+use crate::{Context, Error};
+
+const QUEUE_PAGE_SIZE: usize = 10;
+const SEARCH_RESULTS: u8 = 5;
+const AUTOCOMPLETE_MIN_LEN: usize = 3;
+// seconds forward and rewind jump by default
+const SEEK_STEP: u64 = 10;
+const PLAYLIST_IMPORT: &str = "Importing playlist";
+
+#[poise::command(
+    slash_command,
+    prefix_command,
+    category = "Music",
+    check = "bind_command"
+)]
+pub(crate) async fn join(ctx: Context<'_>) -> Result<(), Error> {
+    ctx.defer_or_broadcast().await?;
+    voice::join_author(&ctx).await?;
+    ctx.say("joined voice channel").await?;
+    Ok(())
+}
+
+#[poise::command(
+    slash_command,
+    prefix_command,
+    category = "Music",
+    check = "bind_command"
+)]
+pub(crate) async fn play(
+    ctx: Context<'_>,
+    #[description = "url or search query"]
+    #[autocomplete = "autocomplete_play"]
+    #[rest]
+    query: String,
+) -> Result<(), Error> {
+    ctx.defer_or_broadcast().await?;
+    let guild_id = ctx.guild_id().ok_or_else(|| Error::Input(NOT_IN_GUILD))?;
+    if let Some(id) = voice::playlist_id(&query) {
+        return import_playlist(&ctx, &id).await;
+    }
+    let url = if voice::is_url(&query) {
+        query
+    } else {
+        match pick_search_result(&ctx, &query).await? {
+            Some(url) => url,
+            // the user didn't pick anything
+            None => return Ok(()),
+        }
+    };
+    let call = voice::get_or_join_call(&ctx).await?;
+    let source = voice::create_source(&url, ctx.data().config.copy_codec()).await?;
+    let handle = voice::enqueue(&call, source, ctx.author().id).await;
+    voice::save_queue(&ctx.data().database, guild_id, &call).await?;
+    let title = handle.metadata().title.clone().unwrap_or(url);
+    ctx.say(format!("queued `{}`", title)).await?;
+    Ok(())
+}
+
+async fn import_playlist(ctx: &Context<'_>, id: &str) -> Result<(), Error> {
+    let entries = voice::playlist_entries(id, ctx.data().config.playlist_limit()).await?;
+    enqueue_entries(ctx, entries, PLAYLIST_IMPORT).await
+}
+
+/// enqueues the entries in the background and shows the progress in a message
+pub(crate) async fn enqueue_entries(
+    ctx: &Context<'_>,
+    entries: Vec<PlaylistEntry>,
+    title: &'static str,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or_else(|| Error::Input(NOT_IN_GUILD))?;
+    if entries.is_empty() {
+        return Err(Error::Input(PLAYLIST_EMPTY));
+    }
+    let call = voice::get_or_join_call(ctx).await?;
+    let color = ctx.data().config.color()?;
+    let embed = voice::import_progress_embed(title, 0, entries.len(), 0, false, color);
+    let reply = ctx
+        .send(|m| {
+            m.embed(|e| {
+                e.clone_from(&embed);
+                e
+            })
+        })
+        .await?;
+    let msg = reply.message().await?;
+    let requester = ctx.author().id;
+    voice::spawn_import(
+        ctx.discord().clone(),
+        ctx.data().clone(),
+        guild_id,
+        call,
+        entries
+            .into_iter()
+            .map(|entry| (entry, requester))
+            .collect(),
+        msg,
+        title,
+    );
+    Ok(())
+}
+
+async fn autocomplete_play(
+    ctx: Context<'_>,
+    partial: String,
+) -> impl Iterator<Item = poise::AutocompleteChoice<String>> {
+    // every search costs youtube api quota, so don't search for every single character
+    let results = if partial.chars().count() < AUTOCOMPLETE_MIN_LEN || voice::is_url(&partial) {
+        vec![]
+    } else {
+        let mut search = YoutubeSearch::new(ctx.data().config.youtube_api_key());
+        search.set_filter(Type::VIDEO).set_amount(SEARCH_RESULTS);
+        match search.search(&partial).await {
+            Ok(response) => response
+                .results()
+                .iter()
+                .map(|result| poise::AutocompleteChoice {
+                    name: result.title().chars().take(100).collect(),
+                    value: result.url(),
+                })
+                .collect(),
+            Err(_) => vec![],
+        }
+    };
+    results.into_iter()
+}
+
+struct SearchMenu {
+    results: Vec<String>,
+    picked: Option<String>,
+}
+
+/// searches youtube for the query and lets the author pick one of the results
+async fn pick_search_result(ctx: &Context<'_>, query: &str) -> Result<Option<String>, Error> {
+    let mut search = YoutubeSearch::new(ctx.data().config.youtube_api_key());
+    search.set_filter(Type::VIDEO).set_amount(SEARCH_RESULTS);
+    let response = search.search(query).await?;
+    let results = response.results();
+    if results.is_empty() {
+        return Err(Error::Input(NO_SEARCH_RESULTS));
+    }
+    let color = ctx.data().config.color()?;
+
+    let mut menu = Menu::new(
+        ctx,
+        SearchMenu {
+            results: results.iter().map(|result| result.url()).collect(),
+            picked: None,
+        },
+        |options| {
+            options
+                .add_row(|row| {
+                    row.add_button(Control::new(
+                        MenuComponent::select("search_pick", |s| {
+                            s.placeholder("pick a video").options(|o| {
+                                for (i, result) in results.iter().enumerate() {
+                                    o.create_option(|op| {
+                                        op.label(format!(
+                                            "{}. {}",
+                                            i + 1,
+                                            result.title().chars().take(90).collect::<String>()
+                                        ))
+                                        .description(
+                                            result
+                                                .channel_name()
+                                                .chars()
+                                                .take(100)
+                                                .collect::<String>(),
+                                        )
+                                        .value(i)
+                                    });
+                                }
+                                o
+                            })
+                        }),
+                        Arc::new(search_pick),
+                    ))
+                })
+                .set_post_hook(Arc::new(search_close))
+        },
+    );
+    menu.run(|m| {
+        for (i, result) in results.iter().enumerate() {
+            m.embed(|e| {
+                e.description(format!("**{}.** {}", i + 1, hyperlink_result(result)))
+                    .thumbnail(result.small_thumbnail().url())
+                    .color(color)
+            });
+        }
+        m
+    })
+    .await?;
+    Ok(menu.data.picked)
+}
+
+fn search_pick<'a>(
+    menu: &'a mut Menu<'_, SearchMenu>,
+    mci: &'a Arc<serenity::MessageComponentInteraction>,
+) -> Pin<Box<dyn Future<Output = Result<(), Error>> + 'a + Send>> {
+    Box::pin(async move {
+        let index = selected_value(mci)?;
+        menu.data.picked = menu.data.results.get(index).cloned();
+        menu.stop();
+        Ok(())
+    })
+}
+
+fn search_close<'a>(
+    menu: &'a mut Menu<'_, SearchMenu>,
+) -> Pin<Box<dyn Future<Output = Result<(), Error>> + 'a + Send>> {
+    Box::pin(async move {
+        if let Some(msg_id) = menu.msg_id {
+            // ignore result, message could be deleted already
+            let _ = menu
+                .ctx
+                .channel_id()
+                .delete_message(&menu.ctx.discord().http, msg_id)
+                .await;
+        }
+        Ok(())
+    })
+}
+
+#[poise::command(
+    slash_command,
+    prefix_command,
+    category = "Music",
+    check = "bind_command"
+)]
+pub(crate) async fn skip(ctx: Context<'_>) -> Result<(), Error> {
+    ctx.defer_or_broadcast().await?;
+    let guild_id = ctx.guild_id().ok_or_else(|| Error::Input(NOT_IN_GUILD))?;
+    let call = voice::get_call(&ctx).await?;
+    let current = call
+        .lock()
+        .await
+        .queue()
+        .current()
+        .ok_or_else(|| Error::Input(NOTHING_PLAYING))?;
+    // DJs and the requester skip right away, everyone else has to vote
+    if !voice::may_modify(&ctx, &[current.clone()]).await? {
+        let listeners = voice::listeners(ctx.discord(), guild_id);
+        if !listeners.contains(&ctx.author().id) {
+            return Err(Error::Input(NOT_LISTENING));
+        }
+        let percent = get_dj_settings(&ctx.data().database, guild_id.0 as i64)
+            .await?
+            .skip_percent as usize;
+        let needed = ((listeners.len() * percent + 99) / 100).max(1);
+        let votes = voice::add_skip_vote(&current, ctx.author().id, &listeners).await?;
+        if votes < needed {
+            ctx.say(format!("voted to skip ({}/{})", votes, needed))
+                .await?;
+            return Ok(());
+        }
+    }
+    {
+        let call = call.lock().await;
+        let queue = call.queue();
+        // the track could've ended while voting
+        if queue.current().map(|handle| handle.uuid()) != Some(current.uuid()) {
+            return Err(Error::Input(NOTHING_PLAYING));
+        }
+        queue.skip()?;
+    }
+    ctx.say("skipped track").await?;
+    Ok(())
+}
+
+#[poise::command(
+    slash_command,
+    prefix_command,
+    category = "Music",
+    check = "bind_command"
+)]
+pub(crate) async fn pause(ctx: Context<'_>) -> Result<(), Error> {
+    ctx.defer_or_broadcast().await?;
+    let call = voice::get_call(&ctx).await?;
+    {
+        let call = call.lock().await;
+        if call.queue().is_empty() {
+            return Err(Error::Input(NOTHING_PLAYING));
+        }
+        call.queue().pause()?;
+    }
+    ctx.say("paused").await?;
+    Ok(())
+}
+
+#[poise::command(
+    slash_command,
+    prefix_command,
+    category = "Music",
+    check = "bind_command"
+)]
+pub(crate) async fn resume(ctx: Context<'_>) -> Result<(), Error> {
+    ctx.defer_or_broadcast().await?;
+    let call = voice::get_call(&ctx).await?;
+    {
+        let call = call.lock().await;
+        if call.queue().is_empty() {
+            return Err(Error::Input(NOTHING_PLAYING));
+        }
+        call.queue().resume()?;
+    }
+    ctx.say("resumed").await?;
+    Ok(())
+}
+
+#[poise::command(
+    slash_command,
+    prefix_command,
+    category = "Music",
+    check = "dj_command"
+)]
+pub(crate) async fn stop(ctx: Context<'_>) -> Result<(), Error> {
+    ctx.defer_or_broadcast().await?;
+    let call = voice::get_call(&ctx).await?;
+    call.lock().await.queue().stop();
+    ctx.say("stopped playing and cleared the queue").await?;
+    Ok(())
+}
+
+#[poise::command(
+    slash_command,
+    prefix_command,
+    category = "Music",
+    check = "dj_command"
+)]
+pub(crate) async fn leave(ctx: Context<'_>) -> Result<(), Error> {
+    ctx.defer_or_broadcast().await?;
+    let guild_id = ctx.guild_id().ok_or_else(|| Error::Input(NOT_IN_GUILD))?;
+    voice::leave(ctx.discord(), &ctx.data().database, guild_id).await?;
+    ctx.say("left voice channel").await?;
+    Ok(())
+}
+
+/// jump to a position of the current track, like `1:30`, `90` or `1m 30s`
+#[poise::command(
+    slash_command,
+    prefix_command,
+    category = "Music",
+    check = "bind_command"
+)]
+pub(crate) async fn seek(
+    ctx: Context<'_>,
+    #[description = "position like 1:30, 90 or 1m 30s"]
+    #[rest]
+    position: String,
+) -> Result<(), Error> {
+    ctx.defer_or_broadcast().await?;
+    let target = parse_timestamp(&position)?;
+    let position = voice::seek_current(&ctx, |_| target).await?;
+    ctx.say(format!("jumped to `{}`", format_duration(&position)))
+        .await?;
+    Ok(())
+}
+
+/// skip forward in the current track
+#[poise::command(
+    slash_command,
+    prefix_command,
+    category = "Music",
+    check = "bind_command"
+)]
+pub(crate) async fn forward(
+    ctx: Context<'_>,
+    #[description = "seconds to skip, 10 by default"] seconds: Option<u64>,
+) -> Result<(), Error> {
+    ctx.defer_or_broadcast().await?;
+    let offset = Duration::from_secs(seconds.unwrap_or(SEEK_STEP));
+    let position = voice::seek_current(&ctx, |position| position + offset).await?;
+    ctx.say(format!("jumped to `{}`", format_duration(&position)))
+        .await?;
+    Ok(())
+}
+
+/// go back in the current track
+#[poise::command(
+    slash_command,
+    prefix_command,
+    category = "Music",
+    check = "bind_command"
+)]
+pub(crate) async fn rewind(
+    ctx: Context<'_>,
+    #[description = "seconds to go back, 10 by default"] seconds: Option<u64>,
+) -> Result<(), Error> {
+    ctx.defer_or_broadcast().await?;
+    let offset = Duration::from_secs(seconds.unwrap_or(SEEK_STEP));
+    let position = voice::seek_current(&ctx, |position| position.saturating_sub(offset)).await?;
+    ctx.say(format!("jumped to `{}`", format_duration(&position)))
+        .await?;
+    Ok(())
+}
+
+/// start the current track from the beginning
+#[poise::command(
+    slash_command,
+    prefix_command,
+    category = "Music",
+    check = "bind_command"
+)]
+pub(crate) async fn replay(ctx: Context<'_>) -> Result<(), Error> {
+    ctx.defer_or_broadcast().await?;
+    voice::seek_current(&ctx, |_| Duration::ZERO).await?;
+    ctx.say("replaying track").await?;
+    Ok(())
+}
+
+/// loop the current track or the whole queue, switches to the next mode without arguments
+#[poise::command(
+    slash_command,
+    prefix_command,
+    category = "Music",
+    rename = "loop",
+    check = "dj_only_command"
+)]
+pub(crate) async fn loop_mode(
+    ctx: Context<'_>,
+    #[description = "off, track or queue"] mode: Option<String>,
+) -> Result<(), Error> {
+    ctx.defer_or_broadcast().await?;
+    let guild_id = ctx.guild_id().ok_or_else(|| Error::Input(NOT_IN_GUILD))?;
+    let mut playback = voice::get_playback_mode(&ctx.data().database, guild_id.0 as i64).await?;
+    playback.loop_mode = match mode {
+        Some(mode) => LoopMode::from_str(&mode.trim().to_lowercase())
+            .map_err(|_| Error::Input(UNKNOWN_LOOP_MODE))?,
+        None => playback.loop_mode.next(),
+    };
+    voice::change_playback_mode(ctx.discord(), ctx.data(), guild_id, playback).await?;
+    ctx.say(format!("loop: {}", playback.loop_mode)).await?;
+    Ok(())
+}
+
+/// keep playing similar videos when the queue runs out, toggles without arguments
+#[poise::command(
+    slash_command,
+    prefix_command,
+    category = "Music",
+    check = "dj_only_command"
+)]
+pub(crate) async fn autoplay(
+    ctx: Context<'_>,
+    #[description = "whether autoplay is on"] enabled: Option<bool>,
+) -> Result<(), Error> {
+    ctx.defer_or_broadcast().await?;
+    let guild_id = ctx.guild_id().ok_or_else(|| Error::Input(NOT_IN_GUILD))?;
+    let mut playback = voice::get_playback_mode(&ctx.data().database, guild_id.0 as i64).await?;
+    playback.autoplay = enabled.unwrap_or(!playback.autoplay);
+    voice::change_playback_mode(ctx.discord(), ctx.data(), guild_id, playback).await?;
+    ctx.say(format!(
+        "autoplay {}",
+        if playback.autoplay { "on" } else { "off" }
+    ))
+    .await?;
+    Ok(())
+}
+
+struct QueueMenu {
+    guild_id: serenity::GuildId,
+    page: usize,
+}
+
+/// browse the queue, jump to or remove tracks, shuffle or clear it
+#[poise::command(
+    slash_command,
+    prefix_command,
+    category = "Music",
+    check = "bind_command"
+)]
+pub(crate) async fn queue(ctx: Context<'_>) -> Result<(), Error> {
+    ctx.defer_or_broadcast().await?;
+    let guild_id = ctx.guild_id().ok_or_else(|| Error::Input(NOT_IN_GUILD))?;
+    let pages = queue_pages(&ctx, guild_id).await?;
+    if pages.is_empty() {
+        return Err(Error::Input(QUEUE_EMPTY));
+    }
+    let color = ctx.data().config.color()?;
+    let embed = queue_embed(&pages, 0, color);
+
+    let mut menu = Menu::new(&ctx, QueueMenu { guild_id, page: 0 }, |options| {
+        options
+            .add_row(|row| {
+                row.add_button(Control::new(
+                    queue_select("queue_jump", "jump to track", &pages[0]),
+                    Arc::new(queue_jump),
+                ))
+            })
+            .add_row(|row| {
+                row.add_button(Control::new(
+                    queue_select("queue_remove", "remove track", &pages[0]),
+                    Arc::new(queue_remove),
+                ))
+            })
+            .add_row(|row| {
+                row.add_button(Control::new(
+                    MenuComponent::button("queue_prev", |b| {
+                        b.label("◀").style(serenity::ButtonStyle::Primary)
+                    }),
+                    Arc::new(queue_prev),
+                ))
+                .add_button(Control::new(
+                    MenuComponent::button("queue_next", |b| {
+                        b.label("▶").style(serenity::ButtonStyle::Primary)
+                    }),
+                    Arc::new(queue_next),
+                ))
+                .add_button(Control::new(
+                    MenuComponent::button("queue_shuffle", |b| {
+                        b.label("shuffle").style(serenity::ButtonStyle::Secondary)
+                    }),
+                    Arc::new(queue_shuffle),
+                ))
+                .add_button(Control::new(
+                    MenuComponent::button("queue_clear", |b| {
+                        b.label("clear").style(serenity::ButtonStyle::Danger)
+                    }),
+                    Arc::new(queue_clear),
+                ))
+            })
+            .set_post_hook(Arc::new(queue_close))
+    });
+    menu.run(|m| {
+        m.embed(|e| {
+            e.clone_from(&embed);
+            e
+        })
+    })
+    .await?;
+    Ok(())
+}
+
+/// splits the queue into pages of (position, title, url, duration)
+async fn queue_pages(
+    ctx: &Context<'_>,
+    guild_id: serenity::GuildId,
+) -> Result<Vec<Vec<(usize, String, Option<String>, Option<Duration>)>>, Error> {
+    let call = voice::get_songbird(ctx.discord())
+        .await?
+        .get(guild_id)
+        .ok_or_else(|| Error::Input(BOT_NOT_IN_VOICE))?;
+    let tracks = call.lock().await.queue().current_queue();
+    Ok(tracks
+        .iter()
+        .enumerate()
+        .map(|(i, handle)| {
+            let metadata = handle.metadata();
+            (
+                i,
+                metadata
+                    .title
+                    .clone()
+                    .unwrap_or_else(|| String::from("unknown title")),
+                metadata.source_url.clone(),
+                metadata.duration,
+            )
+        })
+        .collect::<Vec<_>>()
+        .chunks(QUEUE_PAGE_SIZE)
+        .map(|page| page.to_vec())
+        .collect())
+}
+
+fn queue_embed(
+    pages: &Vec<Vec<(usize, String, Option<String>, Option<Duration>)>>,
+    page: usize,
+    color: serenity::Color,
+) -> CreateEmbed {
+    let mut embed = CreateEmbed::default();
+    embed.title("Queue").color(color);
+    let cursor = Cursor::at(pages, page);
+    match cursor.current() {
+        Some(tracks) => {
+            let lines = tracks
+                .iter()
+                .map(|(i, title, url, duration)| {
+                    let title = match url {
+                        Some(url) => format!("[{}]({})", title, url),
+                        None => title.clone(),
+                    };
+                    let duration = duration
+                        .map(|d| format!(" `{}`", format_duration(&d)))
+                        .unwrap_or_default();
+                    if *i == 0 {
+                        format!("**now playing:** {}{}", title, duration)
+                    } else {
+                        format!("**{}.** {}{}", i, title, duration)
+                    }
+                })
+                .join("\n");
+            embed
+                .description(lines)
+                .footer(|f| f.text(format!("page {}/{}", cursor.index() + 1, pages.len())));
+        }
+        None => {
+            embed.description("the queue is empty");
+        }
+    }
+    embed
+}
+
+fn queue_select(
+    id: &str,
+    placeholder: &str,
+    tracks: &Vec<(usize, String, Option<String>, Option<Duration>)>,
+) -> MenuComponent {
+    MenuComponent::select(id, |s| {
+        s.placeholder(placeholder).options(|o| {
+            for (i, title, _, _) in tracks.iter() {
+                o.create_option(|op| {
+                    op.label(format!(
+                        "{}. {}",
+                        i,
+                        title.chars().take(90).collect::<String>()
+                    ))
+                    .value(i)
+                });
+            }
+            o
+        })
+    })
+}
+
+/// renders the page the menu currently points to
+async fn update_queue_menu(
+    menu: &mut Menu<'_, QueueMenu>,
+    mci: &Arc<serenity::MessageComponentInteraction>,
+    page: impl FnOnce(&mut Cursor<Vec<(usize, String, Option<String>, Option<Duration>)>>),
+) -> Result<(), Error> {
+    if let Ok(call) = voice::get_call(menu.ctx).await {
+        voice::save_queue(&menu.ctx.data().database, menu.data.guild_id, &call).await?;
+    }
+    let pages = queue_pages(menu.ctx, menu.data.guild_id).await?;
+    if pages.is_empty() {
+        menu.stop();
+        return Ok(());
+    }
+    let mut cursor = Cursor::at(&pages, menu.data.page);
+    page(&mut cursor);
+    menu.data.page = cursor.index();
+    if let Some(tracks) = cursor.current() {
+        menu.update_component(
+            "queue_jump",
+            queue_select("queue_jump", "jump to track", tracks),
+        );
+        menu.update_component(
+            "queue_remove",
+            queue_select("queue_remove", "remove track", tracks),
+        );
+    }
+    let embed = queue_embed(&pages, menu.data.page, menu.ctx.data().config.color()?);
+    menu.update_response(|m| m.set_embed(embed), mci).await
+}
+
+fn selected_value(mci: &Arc<serenity::MessageComponentInteraction>) -> Result<usize, Error> {
+    mci.data
+        .values
+        .get(0)
+        .and_then(|value| value.parse::<usize>().ok())
+        .ok_or_else(|| Error::Failure(UNKNOWN_RESPONSE))
+}
+
+/// whether the author may move or remove the tracks at these positions of the queue
+async fn may_modify_tracks(
+    menu: &Menu<'_, QueueMenu>,
+    positions: impl RangeBounds<usize>,
+) -> Result<bool, Error> {
+    let tracks = voice::get_call(menu.ctx)
+        .await?
+        .lock()
+        .await
+        .queue()
+        .current_queue()
+        .into_iter()
+        .enumerate()
+        .filter(|(i, _)| positions.contains(i))
+        .map(|(_, track)| track)
+        .collect::<Vec<_>>();
+    voice::may_modify(menu.ctx, &tracks).await
+}
+
+/// tells the author that only DJs can do that, the menu keeps running
+async fn deny(
+    menu: &Menu<'_, QueueMenu>,
+    mci: &Arc<serenity::MessageComponentInteraction>,
+) -> Result<(), Error> {
+    mci.create_interaction_response(&menu.ctx.discord().http, |ir| {
+        ir.kind(serenity::InteractionResponseType::ChannelMessageWithSource)
+            .interaction_response_data(|d| d.content(ONLY_FOR_DJS).ephemeral(true))
+    })
+    .await?;
+    Ok(())
+}
+
+fn queue_prev<'a>(
+    menu: &'a mut Menu<'_, QueueMenu>,
+    mci: &'a Arc<serenity::MessageComponentInteraction>,
+) -> Pin<Box<dyn Future<Output = Result<(), Error>> + 'a + Send>> {
+    Box::pin(async move {
+        update_queue_menu(menu, mci, |cursor| {
+            cursor.prev();
+        })
+        .await
+    })
+}
+
+fn queue_next<'a>(
+    menu: &'a mut Menu<'_, QueueMenu>,
+    mci: &'a Arc<serenity::MessageComponentInteraction>,
+) -> Pin<Box<dyn Future<Output = Result<(), Error>> + 'a + Send>> {
+    Box::pin(async move {
+        update_queue_menu(menu, mci, |cursor| {
+            cursor.next();
+        })
+        .await
+    })
+}
+
+fn queue_jump<'a>(
+    menu: &'a mut Menu<'_, QueueMenu>,
+    mci: &'a Arc<serenity::MessageComponentInteraction>,
+) -> Pin<Box<dyn Future<Output = Result<(), Error>> + 'a + Send>> {
+    Box::pin(async move {
+        let index = selected_value(mci)?;
+        if index > 0 {
+            // jumping skips the current track
+            if !may_modify_tracks(menu, 0..=0).await?
+                || !may_modify_tracks(menu, index..=index).await?
+            {
+                return deny(menu, mci).await;
+            }
+            let call = voice::get_call(menu.ctx).await?;
+            let call = call.lock().await;
+            let queue = call.queue();
+            // move the track right behind the current one and skip to it
+            queue.modify_queue(|q| {
+                if let Some(track) = q.remove(index) {
+                    q.insert(1, track);
+                }
+            });
+            queue.skip()?;
+        }
+        menu.data.page = 0;
+        update_queue_menu(menu, mci, |_| {}).await
+    })
+}
+
+fn queue_remove<'a>(
+    menu: &'a mut Menu<'_, QueueMenu>,
+    mci: &'a Arc<serenity::MessageComponentInteraction>,
+) -> Pin<Box<dyn Future<Output = Result<(), Error>> + 'a + Send>> {
+    Box::pin(async move {
+        let index = selected_value(mci)?;
+        if !may_modify_tracks(menu, index..=index).await? {
+            return deny(menu, mci).await;
+        }
+        {
+            let call = voice::get_call(menu.ctx).await?;
+            let call = call.lock().await;
+            let queue = call.queue();
+            if index == 0 {
+                queue.skip()?;
+            } else if let Some(track) = queue.dequeue(index) {
+                // the track is still registered at the driver
+                let _ = track.handle().stop();
+            }
+        }
+        update_queue_menu(menu, mci, |_| {}).await
+    })
+}
+
+fn queue_shuffle<'a>(
+    menu: &'a mut Menu<'_, QueueMenu>,
+    mci: &'a Arc<serenity::MessageComponentInteraction>,
+) -> Pin<Box<dyn Future<Output = Result<(), Error>> + 'a + Send>> {
+    Box::pin(async move {
+        if !may_modify_tracks(menu, 1..).await? {
+            return deny(menu, mci).await;
+        }
+        {
+            let call = voice::get_call(menu.ctx).await?;
+            let call = call.lock().await;
+            call.queue().modify_queue(|q| {
+                // the current track stays in front
+                if q.len() > 2 {
+                    q.make_contiguous()[1..].shuffle(&mut rand::thread_rng());
+                }
+            });
+        }
+        menu.data.page = 0;
+        update_queue_menu(menu, mci, |_| {}).await
+    })
+}
+
+fn queue_clear<'a>(
+    menu: &'a mut Menu<'_, QueueMenu>,
+    mci: &'a Arc<serenity::MessageComponentInteraction>,
+) -> Pin<Box<dyn Future<Output = Result<(), Error>> + 'a + Send>> {
+    Box::pin(async move {
+        if !may_modify_tracks(menu, 1..).await? {
+            return deny(menu, mci).await;
+        }
+        {
+            let call = voice::get_call(menu.ctx).await?;
+            let call = call.lock().await;
+            let removed = call.queue().modify_queue(|q| {
+                if q.len() > 1 {
+                    q.drain(1..).collect::<Vec<_>>()
+                } else {
+                    vec![]
+                }
+            });
+            for track in removed {
+                let _ = track.handle().stop();
+            }
+        }
+        menu.data.page = 0;
+        update_queue_menu(menu, mci, |_| {}).await
+    })
+}
+
+fn queue_close<'a>(
+    menu: &'a mut Menu<'_, QueueMenu>,
+) -> Pin<Box<dyn Future<Output = Result<(), Error>> + 'a + Send>> {
+    Box::pin(async move {
+        if let Some(msg_id) = menu.msg_id {
+            // ignore result, message could be deleted already
+            let _ = menu
+                .ctx
+                .channel_id()
+                .delete_message(&menu.ctx.discord().http, msg_id)
+                .await;
+        }
+        Ok(())
+    })
+}
@@ -2,6 +2,7 @@ use crate::{Context, Error};
 
 #[poise::command(prefix_command, owners_only, hide_in_help)]
 pub(crate) async fn shutdown(ctx: Context<'_>) -> Result<(), Error> {
+    crate::voice::save_all_queues(ctx.discord(), &ctx.data().database).await?;
     ctx.framework()
         .shard_manager()
         .lock()
@@ -0,0 +1,628 @@
+use std::{future::Future, pin::Pin, sync::Arc};
+
+use chrono::Utc;
+use poise::serenity_prelude::{self as serenity, CreateEmbed};
+use rand::Rng;
+
+use crate::commands::music::enqueue_entries;
+use crate::menu::{self, Control, Menu, MenuComponent};
+use crate::utils::{bind_command, is_unique_violation};
+use crate::voice::{self, PlaylistEntry};
+use crate::{error::*, Context, Error};
+
+const MAX_PLAYLISTS: usize = 25;
+const MAX_PLAYLIST_LEN: usize = 200;
+const MAX_NAME_LEN: usize = 32;
+const PLAYLIST_PAGE_SIZE: usize = 10;
+const SHARE_CODE_LEN: usize = 6;
+// codes get typed by hand, so there are no characters that look alike (0/O, 1/I/L)
+const SHARE_CODE_CHARS: &[u8] = b"ABCDEFGHJKMNPQRSTUVWXYZ23456789";
+const PLAYLIST_LOADING: &str = "Loading playlist";
+
+pub struct Playlist {
+    pub id: i64,
+    pub name: String,
+    pub share_code: Option<String>,
+}
+
+struct PlaylistMenu {
+    title: String,
+    pages: Vec<String>,
+    page: usize,
+}
+
+/// your saved playlists, lists them without a subcommand
+#[poise::command(
+    slash_command,
+    prefix_command,
+    category = "Music",
+    check = "bind_command",
+    subcommands(
+        "playlist_create",
+        "playlist_add",
+        "playlist_remove",
+        "playlist_list",
+        "playlist_play",
+        "playlist_share"
+    )
+)]
+pub(crate) async fn playlist(ctx: Context<'_>) -> Result<(), Error> {
+    playlist_list_inner(ctx, None).await
+}
+
+/// create a playlist, optionally out of the queue or as copy of a shared playlist
+#[poise::command(
+    slash_command,
+    prefix_command,
+    rename = "create",
+    check = "bind_command"
+)]
+pub(crate) async fn playlist_create(
+    ctx: Context<'_>,
+    #[description = "name of the playlist"] name: String,
+    #[description = "add every track of the queue"] from_queue: Option<bool>,
+    #[description = "copy the tracks of a shared playlist"] share_code: Option<String>,
+) -> Result<(), Error> {
+    ctx.defer_or_broadcast().await?;
+    let name = name.trim();
+    if name.is_empty() || name.chars().count() > MAX_NAME_LEN {
+        return Err(Error::Input(INVALID_PLAYLIST_NAME));
+    }
+    let database = &ctx.data().database;
+    let user_id = ctx.author().id.0 as i64;
+    if get_playlist(database, user_id, name).await?.is_some() {
+        return Err(Error::Input(PLAYLIST_EXISTS));
+    }
+    if get_playlists(database, user_id).await?.len() >= MAX_PLAYLISTS {
+        return Err(Error::Input(TOO_MANY_PLAYLISTS));
+    }
+    let mut entries = vec![];
+    if let Some(code) = share_code {
+        let shared = get_shared_playlist(database, &code)
+            .await?
+            .ok_or_else(|| Error::Input(UNKNOWN_SHARE_CODE))?;
+        entries.extend(get_entries(database, shared.id).await?);
+    }
+    if from_queue == Some(true) {
+        entries.extend(queue_entries(&ctx).await?);
+    }
+    entries.truncate(MAX_PLAYLIST_LEN);
+
+    create_playlist(database, user_id, name, &entries).await?;
+    ctx.say(format!(
+        "created playlist `{}` with {} tracks",
+        name,
+        entries.len()
+    ))
+    .await?;
+    Ok(())
+}
+
+/// add the current track or the whole queue to one of your playlists
+#[poise::command(slash_command, prefix_command, rename = "add", check = "bind_command")]
+pub(crate) async fn playlist_add(
+    ctx: Context<'_>,
+    #[description = "your playlist"]
+    #[autocomplete = "autocomplete_playlist"]
+    playlist: String,
+    #[description = "add every track of the queue instead of the current one"] whole_queue: Option<
+        bool,
+    >,
+) -> Result<(), Error> {
+    ctx.defer_or_broadcast().await?;
+    let database = &ctx.data().database;
+    let playlist = find_playlist(&ctx, &playlist).await?;
+    let added = if whole_queue == Some(true) {
+        queue_entries(&ctx).await?
+    } else {
+        queue_entries(&ctx).await?.into_iter().take(1).collect()
+    };
+    if added.is_empty() {
+        return Err(Error::Input(NOTHING_PLAYING));
+    }
+    let mut entries = get_entries(database, playlist.id).await?;
+    if entries.len() + added.len() > MAX_PLAYLIST_LEN {
+        return Err(Error::Input(PLAYLIST_FULL));
+    }
+    let amount = added.len();
+    entries.extend(added);
+    set_entries(database, playlist.id, &entries).await?;
+    ctx.say(format!("added {} tracks to `{}`", amount, playlist.name))
+        .await?;
+    Ok(())
+}
+
+/// remove a track from one of your playlists or the whole playlist without a position
+#[poise::command(
+    slash_command,
+    prefix_command,
+    rename = "remove",
+    check = "bind_command"
+)]
+pub(crate) async fn playlist_remove(
+    ctx: Context<'_>,
+    #[description = "your playlist"]
+    #[autocomplete = "autocomplete_playlist"]
+    playlist: String,
+    #[description = "position of the track, removes the playlist without"] position: Option<usize>,
+) -> Result<(), Error> {
+    ctx.defer_or_broadcast().await?;
+    let database = &ctx.data().database;
+    let playlist = find_playlist(&ctx, &playlist).await?;
+    match position {
+        Some(position) => {
+            let mut entries = get_entries(database, playlist.id).await?;
+            if position == 0 || position > entries.len() {
+                return Err(Error::Input(INVALID_POSITION));
+            }
+            let removed = entries.remove(position - 1);
+            set_entries(database, playlist.id, &entries).await?;
+            ctx.say(format!(
+                "removed `{}` from `{}`",
+                removed.title.unwrap_or(removed.url),
+                playlist.name
+            ))
+            .await?;
+        }
+        None => {
+            delete_playlist(database, playlist.id).await?;
+            ctx.say(format!("deleted playlist `{}`", playlist.name))
+                .await?;
+        }
+    }
+    Ok(())
+}
+
+/// browse your playlists or the tracks of one of them
+#[poise::command(slash_command, prefix_command, rename = "list", check = "bind_command")]
+pub(crate) async fn playlist_list(
+    ctx: Context<'_>,
+    #[description = "your playlist"]
+    #[autocomplete = "autocomplete_playlist"]
+    playlist: Option<String>,
+) -> Result<(), Error> {
+    playlist_list_inner(ctx, playlist).await
+}
+
+async fn playlist_list_inner(ctx: Context<'_>, playlist: Option<String>) -> Result<(), Error> {
+    ctx.defer_or_broadcast().await?;
+    let database = &ctx.data().database;
+    let (title, lines) = match playlist {
+        Some(playlist) => {
+            let playlist = find_playlist(&ctx, &playlist).await?;
+            let entries = get_entries(database, playlist.id).await?;
+            if entries.is_empty() {
+                return Err(Error::Input(PLAYLIST_EMPTY));
+            }
+            let lines = entries
+                .into_iter()
+                .enumerate()
+                .map(|(i, entry)| {
+                    format!(
+                        "**{}.** [{}]({})",
+                        i + 1,
+                        entry.title.as_deref().unwrap_or("unknown title"),
+                        entry.url
+                    )
+                })
+                .collect::<Vec<_>>();
+            (playlist.name, lines)
+        }
+        None => {
+            let playlists = get_playlists(database, ctx.author().id.0 as i64).await?;
+            if playlists.is_empty() {
+                return Err(Error::Input(NO_PLAYLISTS));
+            }
+            let lines = playlists
+                .into_iter()
+                .map(|(playlist, tracks)| {
+                    let code = playlist
+                        .share_code
+                        .map(|code| format!(" `{}`", code))
+                        .unwrap_or_default();
+                    format!("**{}** {} tracks{}", playlist.name, tracks, code)
+                })
+                .collect::<Vec<_>>();
+            (String::from("Playlists"), lines)
+        }
+    };
+    let pages = lines
+        .chunks(PLAYLIST_PAGE_SIZE)
+        .map(|page| page.join("\n"))
+        .collect::<Vec<_>>();
+    let color = ctx.data().config.color()?;
+    let embed = playlist_embed(&title, &pages, 0, color);
+
+    let mut menu = Menu::new(
+        &ctx,
+        PlaylistMenu {
+            title,
+            pages,
+            page: 0,
+        },
+        |options| {
+            options
+                .add_row(|row| {
+                    row.add_button(Control::new(
+                        MenuComponent::button("playlist_prev", |b| {
+                            b.label("◀").style(serenity::ButtonStyle::Primary)
+                        }),
+                        Arc::new(playlist_prev),
+                    ))
+                    .add_button(Control::new(
+                        MenuComponent::button("playlist_next", |b| {
+                            b.label("▶").style(serenity::ButtonStyle::Primary)
+                        }),
+                        Arc::new(playlist_next),
+                    ))
+                })
+                .set_post_hook(Arc::new(playlist_close))
+        },
+    );
+    menu.run(|m| {
+        m.embed(|e| {
+            e.clone_from(&embed);
+            e
+        })
+    })
+    .await
+}
+
+/// queue every track of one of your playlists or of a shared playlist
+#[poise::command(slash_command, prefix_command, rename = "play", check = "bind_command")]
+pub(crate) async fn playlist_play(
+    ctx: Context<'_>,
+    #[description = "your playlist or a share code"]
+    #[autocomplete = "autocomplete_playlist"]
+    playlist: String,
+) -> Result<(), Error> {
+    ctx.defer_or_broadcast().await?;
+    let database = &ctx.data().database;
+    let user_id = ctx.author().id.0 as i64;
+    let playlist = match get_playlist(database, user_id, playlist.trim()).await? {
+        Some(playlist) => playlist,
+        None => get_shared_playlist(database, &playlist)
+            .await?
+            .ok_or_else(|| Error::Input(UNKNOWN_PLAYLIST))?,
+    };
+    let entries = get_entries(database, playlist.id).await?;
+    enqueue_entries(&ctx, entries, PLAYLIST_LOADING).await
+}
+
+/// get a code that lets everyone play or copy one of your playlists
+#[poise::command(
+    slash_command,
+    prefix_command,
+    rename = "share",
+    check = "bind_command"
+)]
+pub(crate) async fn playlist_share(
+    ctx: Context<'_>,
+    #[description = "your playlist"]
+    #[autocomplete = "autocomplete_playlist"]
+    playlist: String,
+) -> Result<(), Error> {
+    ctx.defer_or_broadcast().await?;
+    let database = &ctx.data().database;
+    let playlist = find_playlist(&ctx, &playlist).await?;
+    let code = match playlist.share_code {
+        Some(code) => code,
+        None => {
+            let mut code = generate_share_code();
+            while get_shared_playlist(database, &code).await?.is_some() {
+                code = generate_share_code();
+            }
+            set_share_code(database, playlist.id, &code).await?;
+            code
+        }
+    };
+    ctx.say(format!(
+        "the share code of `{}` is `{}`, use it with `/playlist play` or `/playlist create`",
+        playlist.name, code
+    ))
+    .await?;
+    Ok(())
+}
+
+fn generate_share_code() -> String {
+    let mut rng = rand::thread_rng();
+    (0..SHARE_CODE_LEN)
+        .map(|_| SHARE_CODE_CHARS[rng.gen_range(0..SHARE_CODE_CHARS.len())] as char)
+        .collect()
+}
+
+/// one of the playlists of the author
+async fn find_playlist(ctx: &Context<'_>, name: &str) -> Result<Playlist, Error> {
+    get_playlist(&ctx.data().database, ctx.author().id.0 as i64, name.trim())
+        .await?
+        .ok_or_else(|| Error::Input(UNKNOWN_PLAYLIST))
+}
+
+/// the tracks of the queue, the current track first
+async fn queue_entries(ctx: &Context<'_>) -> Result<Vec<PlaylistEntry>, Error> {
+    let tracks = voice::get_call(ctx)
+        .await?
+        .lock()
+        .await
+        .queue()
+        .current_queue();
+    Ok(tracks
+        .iter()
+        .filter_map(|handle| {
+            let metadata = handle.metadata();
+            metadata.source_url.clone().map(|url| PlaylistEntry {
+                url,
+                title: metadata.title.clone(),
+            })
+        })
+        .collect())
+}
+
+async fn autocomplete_playlist(
+    ctx: Context<'_>,
+    partial: String,
+) -> impl Iterator<Item = poise::AutocompleteChoice<String>> {
+    let partial = partial.to_lowercase();
+    let playlists = get_playlists(&ctx.data().database, ctx.author().id.0 as i64)
+        .await
+        .unwrap_or_default();
+    playlists
+        .into_iter()
+        .filter(move |(playlist, _)| playlist.name.to_lowercase().contains(&partial))
+        .map(|(playlist, _)| poise::AutocompleteChoice {
+            name: playlist.name.clone(),
+            value: playlist.name,
+        })
+}
+
+fn playlist_embed(
+    title: &str,
+    pages: &Vec<String>,
+    page: usize,
+    color: serenity::Color,
+) -> CreateEmbed {
+    let cursor = menu::Cursor::at(pages, page);
+    let mut embed = CreateEmbed::default();
+    embed
+        .title(title)
+        .color(color)
+        .description(cursor.current().cloned().unwrap_or_default())
+        .footer(|f| f.text(format!("page {}/{}", cursor.index() + 1, pages.len())));
+    embed
+}
+
+async fn update_playlist_menu(
+    menu: &mut Menu<'_, PlaylistMenu>,
+    mci: &Arc<serenity::MessageComponentInteraction>,
+    page: impl FnOnce(&mut menu::Cursor<String>),
+) -> Result<(), Error> {
+    let mut cursor = menu::Cursor::at(&menu.data.pages, menu.data.page);
+    page(&mut cursor);
+    menu.data.page = cursor.index();
+    let embed = playlist_embed(
+        &menu.data.title,
+        &menu.data.pages,
+        menu.data.page,
+        menu.ctx.data().config.color()?,
+    );
+    menu.update_response(|m| m.set_embed(embed), mci).await
+}
+
+fn playlist_prev<'a>(
+    menu: &'a mut Menu<'_, PlaylistMenu>,
+    mci: &'a Arc<serenity::MessageComponentInteraction>,
+) -> Pin<Box<dyn Future<Output = Result<(), Error>> + 'a + Send>> {
+    Box::pin(async move {
+        update_playlist_menu(menu, mci, |cursor| {
+            cursor.prev();
+        })
+        .await
+    })
+}
+
+fn playlist_next<'a>(
+    menu: &'a mut Menu<'_, PlaylistMenu>,
+    mci: &'a Arc<serenity::MessageComponentInteraction>,
+) -> Pin<Box<dyn Future<Output = Result<(), Error>> + 'a + Send>> {
+    Box::pin(async move {
+        update_playlist_menu(menu, mci, |cursor| {
+            cursor.next();
+        })
+        .await
+    })
+}
+
+fn playlist_close<'a>(
+    menu: &'a mut Menu<'_, PlaylistMenu>,
+) -> Pin<Box<dyn Future<Output = Result<(), Error>> + 'a + Send>> {
+    Box::pin(async move {
+        if let Some(msg_id) = menu.msg_id {
+            // ignore result, message could be deleted already
+            let _ = menu
+                .ctx
+                .channel_id()
+                .delete_message(&menu.ctx.discord().http, msg_id)
+                .await;
+        }
+        Ok(())
+    })
+}
+
+/// the playlists of the user with the amount of tracks, sorted by name
+pub async fn get_playlists(
+    database: &sqlx::SqlitePool,
+    user_id: i64,
+) -> Result<Vec<(Playlist, i64)>, Error> {
+    Ok(sqlx::query!(
+        "SELECT playlist.playlist_id, playlist.name, playlist.share_code, COUNT(playlist_entry.url) AS tracks FROM playlist LEFT JOIN playlist_entry ON playlist_entry.playlist_id = playlist.playlist_id WHERE playlist.user_id = ? GROUP BY playlist.playlist_id ORDER BY playlist.name",
+        user_id
+    )
+    .fetch_all(database)
+    .await?
+    .into_iter()
+    .map(|entry| {
+        (
+            Playlist {
+                id: entry.playlist_id,
+                name: entry.name,
+                share_code: entry.share_code,
+            },
+            entry.tracks,
+        )
+    })
+    .collect())
+}
+
+pub async fn get_playlist(
+    database: &sqlx::SqlitePool,
+    user_id: i64,
+    name: &str,
+) -> Result<Option<Playlist>, Error> {
+    Ok(sqlx::query!(
+        "SELECT playlist_id, name, share_code FROM playlist WHERE user_id = ? AND lower(name) = lower(?)",
+        user_id,
+        name
+    )
+    .fetch_optional(database)
+    .await?
+    .map(|entry| Playlist {
+        id: entry.playlist_id,
+        name: entry.name,
+        share_code: entry.share_code,
+    }))
+}
+
+pub async fn get_shared_playlist(
+    database: &sqlx::SqlitePool,
+    code: &str,
+) -> Result<Option<Playlist>, Error> {
+    let code = code.trim().to_uppercase();
+    Ok(sqlx::query!(
+        "SELECT playlist_id, name, share_code FROM playlist WHERE share_code = ?",
+        code
+    )
+    .fetch_optional(database)
+    .await?
+    .map(|entry| Playlist {
+        id: entry.playlist_id,
+        name: entry.name,
+        share_code: entry.share_code,
+    }))
+}
+
+/// creates the playlist together with its tracks
+pub async fn create_playlist(
+    database: &sqlx::SqlitePool,
+    user_id: i64,
+    name: &str,
+    entries: &[PlaylistEntry],
+) -> Result<(), Error> {
+    let created_at = Utc::now().timestamp();
+    let mut tx = database.begin().await?;
+    let playlist_id = sqlx::query!(
+        "INSERT INTO playlist (user_id, name, created_at) VALUES (?, ?, ?)",
+        user_id,
+        name,
+        created_at
+    )
+    .execute(&mut tx)
+    .await
+    // the user could've created it since the name was checked
+    .map_err(|why| {
+        if is_unique_violation(&why) {
+            Error::Input(PLAYLIST_EXISTS)
+        } else {
+            why.into()
+        }
+    })?
+    .last_insert_rowid();
+    insert_entries(&mut tx, playlist_id, entries).await?;
+    tx.commit().await?;
+    Ok(())
+}
+
+pub async fn set_share_code(
+    database: &sqlx::SqlitePool,
+    playlist_id: i64,
+    code: &str,
+) -> Result<(), Error> {
+    sqlx::query!(
+        "UPDATE playlist SET share_code = ? WHERE playlist_id = ?",
+        code,
+        playlist_id
+    )
+    .execute(database)
+    .await?;
+    Ok(())
+}
+
+pub async fn delete_playlist(database: &sqlx::SqlitePool, playlist_id: i64) -> Result<(), Error> {
+    let mut tx = database.begin().await?;
+    sqlx::query!(
+        "DELETE FROM playlist_entry WHERE playlist_id = ?",
+        playlist_id
+    )
+    .execute(&mut tx)
+    .await?;
+    sqlx::query!("DELETE FROM playlist WHERE playlist_id = ?", playlist_id)
+        .execute(&mut tx)
+        .await?;
+    tx.commit().await?;
+    Ok(())
+}
+
+pub async fn get_entries(
+    database: &sqlx::SqlitePool,
+    playlist_id: i64,
+) -> Result<Vec<PlaylistEntry>, Error> {
+    Ok(sqlx::query!(
+        "SELECT url, title FROM playlist_entry WHERE playlist_id = ? ORDER BY position",
+        playlist_id
+    )
+    .fetch_all(database)
+    .await?
+    .into_iter()
+    .map(|entry| PlaylistEntry {
+        url: entry.url,
+        title: entry.title,
+    })
+    .collect())
+}
+
+/// replaces the tracks of the playlist
+pub async fn set_entries(
+    database: &sqlx::SqlitePool,
+    playlist_id: i64,
+    entries: &[PlaylistEntry],
+) -> Result<(), Error> {
+    let mut tx = database.begin().await?;
+    sqlx::query!(
+        "DELETE FROM playlist_entry WHERE playlist_id = ?",
+        playlist_id
+    )
+    .execute(&mut tx)
+    .await?;
+    insert_entries(&mut tx, playlist_id, entries).await?;
+    tx.commit().await?;
+    Ok(())
+}
+
+async fn insert_entries(
+    tx: &mut sqlx::Transaction<'_, sqlx::Sqlite>,
+    playlist_id: i64,
+    entries: &[PlaylistEntry],
+) -> Result<(), Error> {
+    for (position, entry) in entries.iter().enumerate() {
+        let position = position as i64;
+        sqlx::query!(
+            "INSERT INTO playlist_entry (playlist_id, position, url, title) VALUES (?, ?, ?, ?)",
+            playlist_id,
+            position,
+            entry.url,
+            entry.title,
+        )
+        .execute(&mut *tx)
+        .await?;
+    }
+    Ok(())
+}
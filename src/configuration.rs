@@ -1,17 +1,25 @@
 use std::fs::File;
 use std::io::prelude::*;
+use std::time::Duration;
 
 use poise::serenity_prelude::Color;
 use serde::{Deserialize, Serialize};
 
 use crate::Error;
 
+// maximum amount of tracks that get imported from a playlist
+const DEFAULT_PLAYLIST_LIMIT: usize = 50;
+// seconds the bot stays in voice with an empty queue
+const DEFAULT_IDLE_TIMEOUT: u64 = 300;
+
 #[derive(Deserialize, Serialize, Clone)]
 pub struct Config {
     token: String,
     prefix: String,
     application_id: u64,
     copy_codec: Option<bool>,
+    playlist_limit: Option<usize>,
+    idle_timeout: Option<u64>,
     youtube_api_key: String,
     mensa_api_key: Option<String>,
     color: String,
@@ -31,7 +39,6 @@ impl Config {
         &self.application_id
     }
 
-    #[allow(dead_code)]
     pub fn copy_codec(&self) -> bool {
         if let Some(b) = self.copy_codec {
             b
@@ -40,11 +47,22 @@ impl Config {
         }
     }
 
+    pub fn playlist_limit(&self) -> usize {
+        self.playlist_limit.unwrap_or(DEFAULT_PLAYLIST_LIMIT)
+    }
+
+    /// `None` if the bot should never leave because of an empty queue
+    pub fn idle_timeout(&self) -> Option<Duration> {
+        match self.idle_timeout.unwrap_or(DEFAULT_IDLE_TIMEOUT) {
+            0 => None,
+            secs => Some(Duration::from_secs(secs)),
+        }
+    }
+
     pub fn mensa_api_key<'a>(&'a self) -> &'a Option<String> {
         &self.mensa_api_key
     }
 
-    #[allow(dead_code)]
     pub fn youtube_api_key<'a>(&'a self) -> &'a String {
         &self.youtube_api_key
     }
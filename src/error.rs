@@ -9,6 +9,7 @@ use sqlx::Error as SQLError;
 use std::io::Error as IOError;
 use thiserror::Error;
 
+use crate::model::image_processing::ImageProcessingError;
 use crate::{utils::check_result, Context};
 
 pub const CHANNEL_ALREADY_BOUND: &'static str = "channel was already bound";
@@ -18,10 +19,60 @@ pub const NO_BOT_CHANNEL: &'static str =
 pub const WRONG_CHANNEL_TO_BIND: &'static str = "you can only bind text channel";
 pub const NOT_IN_GUILD: &'static str = "only in guilds";
 pub const NOT_IN_VOICE: &'static str = "not in a voice channel";
+pub const BOT_NOT_IN_VOICE: &'static str = "bot is not in a voice channel";
 pub const NO_SEARCH_RESULTS: &'static str = "nothing found";
 pub const NOTHING_PLAYING: &'static str = "nothing playing";
+pub const QUEUE_EMPTY: &'static str = "the queue is empty";
+pub const PLAYLIST_FAILED: &'static str = "couldn't load the playlist";
+pub const PLAYLIST_EMPTY: &'static str = "the playlist is empty";
+pub const UNKNOWN_PLAYLIST: &'static str = "you don't have a playlist with that name";
+pub const NO_PLAYLISTS: &'static str = "you don't have any playlists";
+pub const PLAYLIST_EXISTS: &'static str = "you already have a playlist with that name";
+pub const TOO_MANY_PLAYLISTS: &'static str = "you can't have more than 25 playlists";
+pub const PLAYLIST_FULL: &'static str = "a playlist can't have more than 200 tracks";
+pub const INVALID_PLAYLIST_NAME: &'static str = "playlist names have to be 1 to 32 characters long";
+pub const UNKNOWN_SHARE_CODE: &'static str = "there is no playlist with that share code";
+pub const INVALID_POSITION: &'static str = "the playlist doesn't have a track at that position";
+pub const INVALID_PREFIX: &'static str =
+    "a prefix can't contain whitespaces and has to be 1 to 10 characters long";
+pub const PREFIX_IS_DEFAULT: &'static str = "the default prefix works everywhere anyway";
+pub const PREFIX_ALREADY_ADDED: &'static str = "you already have this prefix";
+pub const PREFIX_TAKEN: &'static str = "this prefix is already taken by another user";
+pub const TOO_MANY_PREFIXES: &'static str = "you can't have more than 3 personal prefixes";
+pub const UNKNOWN_PREFIX: &'static str = "you don't have this prefix";
+pub const NO_OPUS_STREAM: &'static str = "source doesn't provide an opus stream";
+pub const INVALID_TIMESTAMP: &'static str = "the position has to look like 1:30, 90 or 1m 30s";
+pub const SEEK_OUT_OF_RANGE: &'static str = "the track isn't that long";
+pub const NOT_SEEKABLE: &'static str = "can't jump around in this track";
+pub const UNKNOWN_LOOP_MODE: &'static str = "the loop mode has to be off, track or queue";
+pub const ONLY_FOR_DJS: &'static str = "only DJs or the requester of the tracks can do that";
+pub const ONLY_DJS: &'static str = "only DJs can do that";
+pub const NOT_LISTENING: &'static str = "you have to listen to vote";
+pub const ALREADY_VOTED: &'static str = "you already voted to skip this track";
+pub const INVALID_SKIP_PERCENT: &'static str = "the percentage has to be between 1 and 100";
+pub const NOTHING_TO_STEAL: &'static str = "the message has no custom emotes or stickers";
+pub const UNSUPPORTED_STICKER_FORMAT: &'static str =
+    "discord only takes png, apng and gif stickers";
+pub const NOT_A_REPLY: &'static str = "reply to the message you want to steal from";
+pub const NO_EMOTE_SOURCE: &'static str = "upload an image or give an url or a custom emote";
+pub const INVALID_EMOTE_SOURCE: &'static str = "that's neither an url nor a custom emote";
+pub const PRIVATE_EMOTE_SOURCE: &'static str = "the url doesn't point to a public server";
+pub const NOT_AN_IMAGE: &'static str = "the url doesn't point to an image";
+pub const FILE_TOO_LARGE: &'static str = "the file is larger than 10 MB";
+pub const INVALID_ZIP: &'static str = "couldn't read the zip archive";
+pub const NO_IMAGES_IN_ZIP: &'static str = "the archive doesn't contain any images";
+pub const INVALID_EMOTE_NAME: &'static str = "emote names have to be 2 to 32 characters long";
+pub const NO_EMOTE_SLOTS: &'static str = "the server has no free emote slots left";
+pub const UNKNOWN_EMOTE: &'static str = "this server doesn't have that emote";
+pub const NO_EMOTES: &'static str = "this server doesn't have any emotes";
+pub const EXPORT_TOO_LARGE: &'static str = "the export is too large to upload";
+pub const EXPORT_FAILED: &'static str = "couldn't create the zip archive";
 pub const FAILD_TO_GET_SONGBIRD: &'static str = "couldn't get songbird";
 pub const UNKNOWN_WEEKDAY: &'static str = "unknown weekday";
+pub const UNKNOWN_PRICE_TIER: &'static str = "the price has to be student, worker or guest";
+pub const INVALID_TIME: &'static str = "the time has to look like 10:30";
+pub const NO_MENSA_SUBSCRIPTION: &'static str = "the plan isn't posted in this server";
+pub const UNKNOWN_LOCATION: &'static str = "unknown mensa, pick one of the suggestions";
 pub const NO_MENSA_KEY: &'static str = "no mensa key provided";
 pub const UNKNOWN_RESPONSE: &'static str = "got unknown response";
 pub const EVENT_CANCELED: &'static str = "the event was canceled";
@@ -85,6 +136,11 @@ pub enum AyameError {
         #[from]
         source: SQLError,
     },
+    #[error("{}", source)]
+    ImageProcessingError {
+        #[from]
+        source: ImageProcessingError,
+    },
 }
 
 // should be save, because `poise::serenity_prelude::Error` implements it and `&'static str` is
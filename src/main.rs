@@ -1,19 +1,26 @@
+use std::collections::HashMap;
 use std::sync::atomic::AtomicBool;
 use std::sync::atomic::Ordering;
 use std::sync::Arc;
 use std::time::Duration;
 
+use commands::emote::*;
 use commands::general::*;
 use commands::manage::*;
+use commands::mensa::*;
+use commands::music::*;
 use commands::owner::*;
+use commands::playlist::*;
 use poise::serenity_prelude as serenity;
 use songbird::Songbird;
 use songbird::SongbirdKey;
+use tokio::sync::RwLock;
 use tracing::{error, info};
 
 mod commands;
 mod configuration;
 mod error;
+mod mensa;
 mod menu;
 mod model;
 mod utils;
@@ -28,27 +35,63 @@ pub struct Data {
     config: Arc<configuration::Config>,
     // database
     database: sqlx::SqlitePool,
+    // cached guild prefixes, `None` if the guild uses the default prefix
+    guild_prefixes: Arc<RwLock<HashMap<serenity::GuildId, Option<String>>>>,
+    // cached personal prefixes of users, longest prefix first
+    user_prefixes: Arc<RwLock<HashMap<serenity::UserId, Vec<String>>>>,
+    // mensa plans of every location
+    mensa_plans: mensa::PlanCache,
 }
 pub type Error = error::AyameError;
 
 pub type Context<'a> = poise::Context<'a, Data, Error>;
 
 async fn event_listener(
-    _ctx: &serenity::Context,
+    ctx: &serenity::Context,
     event: &poise::Event<'_>,
     _framework: &poise::Framework<Data, Error>,
-    _data: &Data,
+    data: &Data,
 ) -> Result<(), Error> {
     match event {
         poise::Event::Ready { data_about_bot } => {
             info!("{} is connected!", data_about_bot.user.name)
         }
+        poise::Event::Message { new_message } => {
+            cleanup_bound_channel(ctx, data, new_message).await?
+        }
+        poise::Event::InteractionCreate {
+            interaction: serenity::Interaction::MessageComponent(mci),
+        } => voice::handle_status_button(ctx, data, mci).await?,
+        poise::Event::VoiceStateUpdate { new, .. } => {
+            voice::handle_voice_state_update(ctx, data, new).await?
+        }
         _ => {}
     }
 
     Ok(())
 }
 
+/// strips the guild prefix (or the default prefix) or one of the personal prefixes of the
+/// author. The longest matching prefix wins, so `!!` isn't read as `!` followed by `!`
+async fn strip_prefix<'a>(
+    _ctx: &'a serenity::Context,
+    msg: &'a serenity::Message,
+    data: &'a Data,
+) -> Result<Option<(&'a str, &'a str)>, Error> {
+    let guild_prefix = match msg.guild_id {
+        Some(guild_id) => get_guild_prefix(data, guild_id).await?,
+        None => None,
+    };
+    let mut prefixes = vec![guild_prefix.unwrap_or_else(|| data.config.prefix().clone())];
+    prefixes.extend(get_user_prefixes(data, msg.author.id).await?);
+    // stable, so the guild prefix still comes first among prefixes of the same length
+    prefixes.sort_by_key(|prefix| std::cmp::Reverse(prefix.len()));
+    Ok(prefixes
+        .iter()
+        .find(|prefix| msg.content.starts_with(prefix.as_str()))
+        .map(|prefix| msg.content.split_at(prefix.len())))
+}
+
 #[poise::command(prefix_command, slash_command, ephemeral)]
 async fn help(
     ctx: Context<'_>,
@@ -107,6 +150,12 @@ async fn main() -> Result<(), anyhow::Error> {
         )
         .await?;
     sqlx::migrate!("./migrations").run(&database).await?;
+    // nothing can use the temporary files of the last run anymore
+    match model::temp::sweep_tmp_dir() {
+        Ok(removed) if removed > 0 => info!("removed {} stale temporary files", removed),
+        Ok(_) => {}
+        Err(why) => error!("couldn't clean up temporary files: {:?}", why),
+    }
 
     Ok(run_discord_client(database).await?)
 }
@@ -127,7 +176,7 @@ async fn run_discord_client(database: sqlx::SqlitePool) -> Result<(), anyhow::Er
                 .type_map_insert::<SongbirdKey>(voice)
         })
         .token(config.token())
-        .options(get_discord_configuration(&config))
+        .options(get_discord_configuration())
         .user_data_setup(|ctx, _data_about_bot, framework| {
             Box::pin(async move {
                 // set activity to "{prefix}help"
@@ -137,13 +186,16 @@ async fn run_discord_client(database: sqlx::SqlitePool) -> Result<(), anyhow::Er
                 )))
                 .await;
                 let shard_manager = framework.shard_manager();
+                let (discord, pool) = (ctx.clone(), database.clone());
                 tokio::spawn(async move {
                     tokio::signal::ctrl_c()
                         .await
                         .expect("Could not register ctrl+c handler");
+                    utils::check_result_ayame(voice::save_all_queues(&discord, &pool).await);
                     shard_manager.lock().await.shutdown_all().await;
                 });
                 let shard_manager = framework.shard_manager();
+                let (discord, pool) = (ctx.clone(), database.clone());
                 tokio::spawn(async move {
                     let term = Arc::new(AtomicBool::new(false));
 
@@ -156,22 +208,32 @@ async fn run_discord_client(database: sqlx::SqlitePool) -> Result<(), anyhow::Er
                     while !term.load(Ordering::Relaxed) {
                         tokio::time::sleep(Duration::from_secs(2)).await;
                     }
+                    utils::check_result_ayame(voice::save_all_queues(&discord, &pool).await);
                     shard_manager.lock().await.shutdown_all().await;
                 });
+                // status messages of the last run can't be updated anymore
+                utils::check_result_ayame(voice::cleanup_status_messages(ctx, &database).await);
                 // create our data
-                Ok(Data {
+                let data = Data {
                     config: Arc::new(config),
                     database,
-                })
+                    guild_prefixes: Arc::new(RwLock::new(HashMap::new())),
+                    user_prefixes: Arc::new(RwLock::new(HashMap::new())),
+                    mensa_plans: mensa::PlanCache::default(),
+                };
+                utils::check_result_ayame(voice::offer_queue_restores(ctx, &data).await);
+                if let Some(mensa_key) = data.config.mensa_api_key() {
+                    data.mensa_plans.spawn_refresh(mensa_key.clone());
+                }
+                start_mensa_scheduler(ctx, &data);
+                Ok(data)
             })
         });
 
     Ok(client.run_autosharded().await?)
 }
 
-fn get_discord_configuration(
-    config: &configuration::Config,
-) -> poise::FrameworkOptions<Data, Error> {
+fn get_discord_configuration() -> poise::FrameworkOptions<Data, Error> {
     poise::FrameworkOptions {
         commands: vec![
             avatar(),
@@ -184,10 +246,33 @@ fn get_discord_configuration(
             unregister(),
             bind(),
             ping_bind(),
+            bind_settings(),
+            dj_settings(),
+            prefix(),
+            myprefix(),
             mensa(),
             invite(),
             shutdown(),
             addemote(),
+            steal_emote(),
+            steal(),
+            importemotes(),
+            emote(),
+            join(),
+            play(),
+            skip(),
+            pause(),
+            resume(),
+            stop(),
+            leave(),
+            queue(),
+            seek(),
+            forward(),
+            rewind(),
+            replay(),
+            loop_mode(),
+            autoplay(),
+            playlist(),
         ],
         listener: |ctx, event, framework, user_data| {
             Box::pin(event_listener(ctx, event, framework, user_data))
@@ -195,7 +280,9 @@ fn get_discord_configuration(
         on_error: |error| Box::pin(on_error(error)),
         // Options specific to prefix commands, i.e. commands invoked via chat messages
         prefix_options: poise::PrefixFrameworkOptions {
-            prefix: Some(String::from(config.prefix())),
+            // the default prefix gets resolved in `strip_prefix` because guilds can override it
+            prefix: None,
+            stripped_dynamic_prefix: Some(|ctx, msg, data| Box::pin(strip_prefix(ctx, msg, data))),
 
             mention_as_prefix: true,
             // An edit tracker needs to be supplied here to make edit tracking in commands work
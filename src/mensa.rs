@@ -0,0 +1,217 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Datelike, Utc};
+use chrono_tz::Tz;
+use mensa_swfr_rs::mensa::Plan;
+use tokio::sync::RwLock;
+use tracing::error;
+use url::Url;
+
+use crate::error::*;
+use crate::Error;
+
+const API_URL: &str = "https://www.swfr.de/apispeiseplan";
+// the plan rarely changes during the day
+const PLAN_TTL: Duration = Duration::from_secs(60 * 60);
+// how long an outdated plan is shown while the api is down
+const MAX_STALE_AGE: Duration = Duration::from_secs(24 * 60 * 60);
+// the servers run in UTC, but the plan and the scheduled posts follow the local time of the mensa
+const TIMEZONE: Tz = chrono_tz::Europe::Berlin;
+
+/// the current time at the mensa
+pub fn now() -> DateTime<Tz> {
+    Utc::now().with_timezone(&TIMEZONE)
+}
+
+pub struct Location {
+    pub id: u32,
+    pub name: &'static str,
+}
+
+// the `ort` ids of the speiseplan api of the SWFR (`API_URL`), the api has no endpoint that
+// lists them. `test_request_plans` checks that every one of them still returns a plan
+pub const LOCATIONS: [Location; 4] = [
+    Location {
+        id: 610,
+        name: "Rempartstraße",
+    },
+    Location {
+        id: 620,
+        name: "Institutsviertel",
+    },
+    Location {
+        id: 630,
+        name: "Littenweiler",
+    },
+    Location {
+        id: 640,
+        name: "Flugplatz",
+    },
+];
+
+/// used when neither the user nor the guild picked a location
+pub const DEFAULT_LOCATION: &Location = &LOCATIONS[0];
+
+pub fn location_by_id(id: u32) -> Option<&'static Location> {
+    LOCATIONS.iter().find(|location| location.id == id)
+}
+
+/// finds a location by its id or the start of its name
+pub fn find_location(query: &str) -> Option<&'static Location> {
+    let query = query.trim().to_lowercase();
+    if query.is_empty() {
+        return None;
+    }
+    LOCATIONS.iter().find(|location| {
+        location.id.to_string() == query || location.name.to_lowercase().starts_with(&query)
+    })
+}
+
+/// a plan out of the cache
+#[derive(Clone)]
+pub struct CachedPlan {
+    pub plan: Arc<Plan>,
+    pub fetched_at: DateTime<Tz>,
+    // the plan couldn't be refreshed, it's older than `PLAN_TTL`
+    pub stale: bool,
+    age: Instant,
+}
+
+// location id with the iso year and week
+type PlanKey = (u32, i32, u32);
+
+fn plan_key(location_id: u32) -> PlanKey {
+    let week = now().iso_week();
+    (location_id, week.year(), week.week())
+}
+
+/// the plans of every location and week, shared by all users
+#[derive(Clone, Default)]
+pub struct PlanCache {
+    plans: Arc<RwLock<HashMap<PlanKey, CachedPlan>>>,
+}
+
+impl PlanCache {
+    /// the cached plan of this week, it gets fetched if it's older than `PLAN_TTL`. If the api
+    /// fails the old plan is used for up to `MAX_STALE_AGE`
+    pub async fn get(&self, mensa_key: &str, location: &Location) -> Result<CachedPlan, Error> {
+        let key = plan_key(location.id);
+        let cached = self.plans.read().await.get(&key).cloned();
+        if let Some(cached) = &cached {
+            if cached.age.elapsed() < PLAN_TTL {
+                return Ok(cached.clone());
+            }
+        }
+        match self.refresh(mensa_key, location).await {
+            Ok(plan) => Ok(plan),
+            Err(why) => match cached {
+                Some(cached) if cached.age.elapsed() < MAX_STALE_AGE => {
+                    error!("using outdated plan of {}: {:?}", location.name, why);
+                    Ok(CachedPlan {
+                        stale: true,
+                        ..cached
+                    })
+                }
+                _ => Err(why),
+            },
+        }
+    }
+
+    async fn refresh(&self, mensa_key: &str, location: &Location) -> Result<CachedPlan, Error> {
+        let cached = CachedPlan {
+            plan: Arc::new(request_plan(mensa_key, location).await?),
+            fetched_at: now(),
+            stale: false,
+            age: Instant::now(),
+        };
+        self.plans
+            .write()
+            .await
+            .insert(plan_key(location.id), cached.clone());
+        Ok(cached)
+    }
+
+    /// refreshes the plans of every location that was looked up this week before they expire,
+    /// so nobody has to wait for the api. Plans of past weeks get dropped
+    pub fn spawn_refresh(&self, mensa_key: String) {
+        let cache = self.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(PLAN_TTL / 2);
+            loop {
+                interval.tick().await;
+                let locations = {
+                    let mut plans = cache.plans.write().await;
+                    plans.retain(|key, _| *key == plan_key(key.0));
+                    plans
+                        .keys()
+                        .filter_map(|key| location_by_id(key.0))
+                        .collect::<Vec<_>>()
+                };
+                for location in locations {
+                    if let Err(why) = cache.refresh(&mensa_key, location).await {
+                        error!("couldn't refresh plan of {}: {:?}", location.name, why);
+                    }
+                }
+            }
+        });
+    }
+}
+
+/// `mensa_swfr_rs` only knows the rempart, so the other locations are requested like it does
+async fn request_plan(mensa_key: &str, location: &Location) -> Result<Plan, Error> {
+    let xml = reqwest::get(plan_url(mensa_key, location))
+        .await?
+        .error_for_status()?
+        .text()
+        .await?;
+    serde_xml_rs::from_str(&xml).map_err(|_| Error::Failure(UNKNOWN_RESPONSE))
+}
+
+/// the same url `mensa_swfr_rs` requests, with the location as `ort` parameter
+fn plan_url(mensa_key: &str, location: &Location) -> Url {
+    let mut url = Url::parse(API_URL).expect("invalid mensa api url");
+    url.query_pairs_mut()
+        .append_pair("type", "98")
+        .append_pair("tx_speiseplan_pi1[apiKey]", mensa_key)
+        .append_pair("tx_speiseplan_pi1[ort]", &location.id.to_string());
+    url
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::mensa::*;
+
+    #[test]
+    fn test_plan_url() {
+        let location = location_by_id(620).unwrap();
+        let url = plan_url("key", location);
+        let pairs = url.query_pairs().into_owned().collect::<HashMap<_, _>>();
+        assert_eq!(pairs["type"], "98");
+        assert_eq!(pairs["tx_speiseplan_pi1[apiKey]"], "key");
+        assert_eq!(pairs["tx_speiseplan_pi1[ort]"], "620");
+    }
+
+    #[test]
+    fn test_find_location() {
+        assert_eq!(find_location("630").map(|l| l.id), Some(630));
+        assert_eq!(find_location("insti").map(|l| l.id), Some(620));
+        assert_eq!(find_location(" FLUG").map(|l| l.id), Some(640));
+        assert!(find_location("").is_none());
+        assert!(find_location("650").is_none());
+    }
+
+    #[tokio::test]
+    // github run fails because no config.toml is provided
+    #[ignore]
+    async fn test_request_plans() -> Result<(), Box<dyn std::error::Error>> {
+        let config = &crate::configuration::config();
+        let mensa_key = config.mensa_api_key().as_ref().expect("no mensa key");
+        for location in LOCATIONS.iter() {
+            let plan = request_plan(mensa_key, location).await?;
+            assert!(!plan.days().is_empty(), "no days for {}", location.name);
+        }
+        Ok(())
+    }
+}
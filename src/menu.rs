@@ -37,9 +37,11 @@ impl<'a, T> Menu<'a, T> {
         if let Some(pre_hook) = &self.options.pre_hook {
             Arc::clone(pre_hook)(self).await?;
         }
+        // other messages in the channel have buttons too, e.g. the status message
         while let Some(mci) = serenity::CollectComponentInteraction::new(self.ctx.discord())
             .author_id(self.ctx.author().id)
             .channel_id(self.ctx.channel_id())
+            .message_id(msg_id)
             .timeout(std::time::Duration::from_secs(self.options.timeout))
             .await
         {
@@ -110,12 +112,33 @@ impl<'a, T> Menu<'a, T> {
     ) -> Result<(), Error> {
         mci.create_interaction_response(&self.ctx.discord(), |ir| {
             ir.kind(serenity::InteractionResponseType::UpdateMessage)
-                .interaction_response_data(|m| f(m))
+                .interaction_response_data(|m| {
+                    f(m).components(|cs| {
+                        for row in self.options.controls.iter() {
+                            cs.add_action_row(row.action_row());
+                        }
+                        cs
+                    })
+                })
         })
         .await?;
         Ok(())
     }
 
+    /// replaces the component of a control, the function of the control stays the same
+    pub fn update_component(&mut self, id: &str, component: MenuComponent) {
+        if let Some(ctrl) = self
+            .options
+            .controls
+            .iter_mut()
+            .map(|row| row.buttons.iter_mut())
+            .flatten()
+            .find(|ctrl| ctrl.button.id() == id)
+        {
+            ctrl.button = component;
+        }
+    }
+
     pub fn stop(&mut self) {
         self.is_runnig = false;
     }
@@ -252,7 +275,6 @@ pub enum MenuComponent {
         create: serenity::CreateButton,
         id: String,
     },
-    #[allow(dead_code)]
     SelectComponent {
         create: serenity::CreateSelectMenu,
         id: String,
@@ -278,7 +300,6 @@ impl MenuComponent {
             id: id.to_string(),
         }
     }
-    #[allow(dead_code)]
     pub fn select<F>(id: &str, f: F) -> MenuComponent
     where
         F: FnOnce(&mut serenity::CreateSelectMenu) -> &mut serenity::CreateSelectMenu,
@@ -323,7 +344,19 @@ impl<'a, T> Cursor<'a, T> {
             current_index: 0,
         }
     }
+
+    /// creates a cursor that starts at `index`, clamped to the last element
+    pub fn at(list: &'a Vec<T>, index: usize) -> Self {
+        Self {
+            list,
+            current_index: index.min(list.len().saturating_sub(1)),
+        }
+    }
+
     pub fn next(&mut self) -> Option<&'a T> {
+        if self.list.is_empty() {
+            return None;
+        }
         self.current_index = if self.current_index >= self.list.len() - 1 {
             0
         } else {
@@ -333,6 +366,9 @@ impl<'a, T> Cursor<'a, T> {
     }
 
     pub fn prev(&mut self) -> Option<&'a T> {
+        if self.list.is_empty() {
+            return None;
+        }
         self.current_index = if self.current_index <= 0 {
             self.list.len() - 1
         } else {
@@ -344,6 +380,10 @@ impl<'a, T> Cursor<'a, T> {
     pub fn current(&self) -> Option<&'a T> {
         self.list.get(self.current_index)
     }
+
+    pub fn index(&self) -> usize {
+        self.current_index
+    }
 }
 
 impl<'a, T> From<&'a Vec<T>> for Cursor<'a, T> {
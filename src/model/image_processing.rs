@@ -1,10 +1,454 @@
 use std::fs;
+use std::io::Cursor;
 use std::path::PathBuf;
 
+use image::codecs::gif::{GifDecoder, GifEncoder, Repeat};
+use image::codecs::png::PngDecoder;
+use image::codecs::webp::WebPDecoder;
+use image::imageops::{self, FilterType};
+use image::{
+    AnimationDecoder, Delay, DynamicImage, Frame, Frames, GenericImageView, ImageFormat,
+    ImageOutputFormat,
+};
+use thiserror::Error;
+
 use crate::Error;
 
-const MAX_EMOTE_SIZE: u64 = 256_000; //kb
+pub const MAX_EMOTE_SIZE: u64 = 256_000; // bytes
+
+// discord doesn't display emotes bigger than 128px anyway
+const MAX_EMOTE_DIMENSION: u32 = 128;
+const MIN_EMOTE_DIMENSION: u32 = 32;
+const DIMENSION_STEP: u32 = 16;
+const JPEG_QUALITIES: [u8; 4] = [90, 75, 60, 45];
+// (keep every nth frame, dropped color bits) tried for every dimension of an animation
+const ANIMATION_STEPS: [(usize, u8); 5] = [(1, 0), (1, 2), (2, 2), (2, 3), (3, 3)];
+// 1 is the best palette and 30 the fastest, 10 is the default of the gif crate
+const GIF_ENCODER_SPEED: i32 = 10;
+// checked before decoding, so a tiny file can't make the bot allocate gigabytes
+const MAX_FRAME_PIXELS: u64 = 4096 * 4096;
+// summed over all frames of an animation
+const MAX_ANIMATION_PIXELS: u64 = 32_000_000;
+
+#[derive(Error, Debug)]
+pub enum ImageProcessingError {
+    #[error("couldn't decode the image: {0}")]
+    Decode(#[from] image::ImageError),
+    #[error("the emote is still {size} bytes at {width}x{height}, the limit is {max} bytes", max = MAX_EMOTE_SIZE)]
+    TooLarge { size: u64, width: u32, height: u32 },
+    #[error("the image has too many pixels or frames to process")]
+    TooManyPixels,
+}
+
+/// the emote file that fits into discord's size limit
+pub struct ReducedEmote {
+    pub path: PathBuf,
+    pub size: u64,
+    pub width: u32,
+    pub height: u32,
+    // takes an animated emote slot
+    pub animated: bool,
+}
+
+/// downscales and re-encodes the image until it fits into `MAX_EMOTE_SIZE`, files that are
+/// small enough are returned unchanged. Animated images (gif, apng and webp) stay animated and
+/// always end up as gif, because discord only animates gif emotes
+pub fn reduce_emote_size(img: &PathBuf) -> Result<ReducedEmote, Error> {
+    let bytes = fs::read(img)?;
+    let format = image::guess_format(&bytes).map_err(ImageProcessingError::Decode)?;
+    let (width, height) = image::io::Reader::with_format(Cursor::new(&bytes), format)
+        .into_dimensions()
+        .map_err(ImageProcessingError::Decode)?;
+    if width as u64 * height as u64 > MAX_FRAME_PIXELS {
+        return Err(ImageProcessingError::TooManyPixels.into());
+    }
+    let frames = animation_frames(&bytes, format)?;
+    let size = bytes.len() as u64;
+    if size <= MAX_EMOTE_SIZE && (frames.is_none() || format == ImageFormat::Gif) {
+        return Ok(ReducedEmote {
+            path: img.clone(),
+            size,
+            width,
+            height,
+            animated: is_animated(&bytes),
+        });
+    }
+
+    // a single frame gif ends up as still image
+    let animated = frames.is_some();
+    let (encoded, extension, width, height) = match frames {
+        Some(frames) => {
+            let (encoded, width, height) = shrink_animated(frames)?;
+            (encoded, "gif", width, height)
+        }
+        None => {
+            let image = image::load_from_memory_with_format(&bytes, format)
+                .map_err(ImageProcessingError::Decode)?;
+            shrink_static(&image)?
+        }
+    };
+    let path = img.with_extension(extension);
+    fs::write(&path, &encoded)?;
+    Ok(ReducedEmote {
+        path,
+        size: encoded.len() as u64,
+        width,
+        height,
+        animated,
+    })
+}
+
+/// whether discord would treat the image as animated emote
+pub fn is_animated(bytes: &[u8]) -> bool {
+    match image::guess_format(bytes) {
+        // discord always treats gifs as animated emotes
+        Ok(ImageFormat::Gif) => true,
+        Ok(ImageFormat::Png) => PngDecoder::new(Cursor::new(bytes))
+            .map(|decoder| decoder.is_apng())
+            .unwrap_or(false),
+        Ok(ImageFormat::WebP) => WebPDecoder::new(Cursor::new(bytes))
+            .map(|decoder| decoder.has_animation())
+            .unwrap_or(false),
+        _ => false,
+    }
+}
+
+/// the file extension that matches the content of the image
+pub fn image_extension(bytes: &[u8]) -> Option<&'static str> {
+    match image::guess_format(bytes).ok()? {
+        ImageFormat::Gif => Some("gif"),
+        ImageFormat::Png => Some("png"),
+        ImageFormat::WebP => Some("webp"),
+        ImageFormat::Jpeg => Some("jpg"),
+        _ => None,
+    }
+}
+
+/// decodes all frames of an animated image, `None` if the image isn't animated
+fn animation_frames(
+    bytes: &[u8],
+    format: ImageFormat,
+) -> Result<Option<Vec<Frame>>, ImageProcessingError> {
+    let frames = match format {
+        ImageFormat::Gif => collect_frames(GifDecoder::new(Cursor::new(bytes))?.into_frames())?,
+        ImageFormat::Png => {
+            let decoder = PngDecoder::new(Cursor::new(bytes))?;
+            if !decoder.is_apng() {
+                return Ok(None);
+            }
+            collect_frames(decoder.apng().into_frames())?
+        }
+        ImageFormat::WebP => {
+            let decoder = WebPDecoder::new(Cursor::new(bytes))?;
+            if !decoder.has_animation() {
+                return Ok(None);
+            }
+            collect_frames(decoder.into_frames())?
+        }
+        _ => return Ok(None),
+    };
+    Ok(if frames.len() > 1 { Some(frames) } else { None })
+}
+
+/// decodes frame by frame and stops once the animation exceeds `MAX_ANIMATION_PIXELS`
+fn collect_frames(frames: Frames) -> Result<Vec<Frame>, ImageProcessingError> {
+    let mut collected = vec![];
+    let mut pixels = 0;
+    for frame in frames {
+        let frame = frame?;
+        let (width, height) = frame.buffer().dimensions();
+        pixels += width as u64 * height as u64;
+        if pixels > MAX_ANIMATION_PIXELS {
+            return Err(ImageProcessingError::TooManyPixels);
+        }
+        collected.push(frame);
+    }
+    Ok(collected)
+}
+
+/// every dimension that gets tried, from the biggest useful one down to `MIN_EMOTE_DIMENSION`
+fn dimension_steps(width: u32, height: u32) -> Vec<u32> {
+    let mut dimension = width.max(height).min(MAX_EMOTE_DIMENSION);
+    let mut steps = vec![dimension];
+    while dimension > MIN_EMOTE_DIMENSION {
+        dimension = dimension
+            .saturating_sub(DIMENSION_STEP)
+            .max(MIN_EMOTE_DIMENSION);
+        steps.push(dimension);
+    }
+    steps
+}
+
+/// tries png first and falls back to jpeg with decreasing quality for opaque images, before
+/// trying the next smaller dimension
+fn shrink_static(
+    image: &DynamicImage,
+) -> Result<(Vec<u8>, &'static str, u32, u32), ImageProcessingError> {
+    let (orig_width, orig_height) = image.dimensions();
+    let mut smallest = None;
+    let (mut width, mut height) = (orig_width, orig_height);
+    for dimension in dimension_steps(orig_width, orig_height) {
+        let resized = image.resize(dimension, dimension, FilterType::Lanczos3);
+        width = resized.width();
+        height = resized.height();
+        let mut candidates = vec![(encode(&resized, ImageOutputFormat::Png)?, "png")];
+        if !resized.color().has_alpha() {
+            let rgb = DynamicImage::ImageRgb8(resized.to_rgb8());
+            for quality in JPEG_QUALITIES {
+                candidates.push((encode(&rgb, ImageOutputFormat::Jpeg(quality))?, "jpg"));
+            }
+        }
+        for (encoded, extension) in candidates {
+            let size = encoded.len() as u64;
+            if size <= MAX_EMOTE_SIZE {
+                return Ok((encoded, extension, width, height));
+            }
+            smallest = Some(smallest.map_or(size, |s: u64| s.min(size)));
+        }
+    }
+    Err(ImageProcessingError::TooLarge {
+        size: smallest.unwrap_or_default(),
+        width,
+        height,
+    })
+}
+
+/// shrinks an animation without losing the animation. For every dimension it tries dropping
+/// frames and throwing away color bits, ordered by how much quality gets lost
+fn shrink_animated(frames: Vec<Frame>) -> Result<(Vec<u8>, u32, u32), ImageProcessingError> {
+    let (orig_width, orig_height) = frames[0].buffer().dimensions();
+    let mut smallest = None;
+    let (mut width, mut height) = (orig_width, orig_height);
+    for dimension in dimension_steps(orig_width, orig_height) {
+        // keeps the aspect ratio like `DynamicImage::resize`
+        let scale = dimension as f64 / orig_width.max(orig_height) as f64;
+        width = ((orig_width as f64 * scale).round() as u32).max(1);
+        height = ((orig_height as f64 * scale).round() as u32).max(1);
+        let resized = frames
+            .iter()
+            .map(|frame| {
+                let buffer = imageops::resize(frame.buffer(), width, height, FilterType::Triangle);
+                Frame::from_parts(buffer, 0, 0, frame.delay())
+            })
+            .collect::<Vec<_>>();
+
+        for (frame_step, dropped_bits) in ANIMATION_STEPS {
+            // a single frame would just be a still image
+            if frame_step > 1 && resized.len() / frame_step < 2 {
+                continue;
+            }
+            let encoded = encode_gif(reduce_frames(&resized, frame_step, dropped_bits))?;
+            let size = encoded.len() as u64;
+            if size <= MAX_EMOTE_SIZE {
+                return Ok((encoded, width, height));
+            }
+            smallest = Some(smallest.map_or(size, |s: u64| s.min(size)));
+        }
+    }
+    Err(ImageProcessingError::TooLarge {
+        size: smallest.unwrap_or_default(),
+        width,
+        height,
+    })
+}
+
+/// keeps every `frame_step`th frame, the delays of dropped frames get added to the previous kept
+/// frame so the animation keeps its speed. `dropped_bits` lowest bits of every color channel are
+/// cleared, which leaves fewer colors for the palette and compresses better
+fn reduce_frames(frames: &[Frame], frame_step: usize, dropped_bits: u8) -> Vec<Frame> {
+    let mask = !((1u8 << dropped_bits) - 1);
+    frames
+        .chunks(frame_step)
+        .map(|chunk| {
+            let delay_ms = chunk
+                .iter()
+                .map(|frame| {
+                    let (numer, denom) = frame.delay().numer_denom_ms();
+                    numer as f64 / denom.max(1) as f64
+                })
+                .sum::<f64>();
+            let mut buffer = chunk[0].buffer().clone();
+            if dropped_bits > 0 {
+                for pixel in buffer.pixels_mut() {
+                    for channel in pixel.0.iter_mut().take(3) {
+                        *channel &= mask;
+                    }
+                }
+            }
+            Frame::from_parts(
+                buffer,
+                0,
+                0,
+                Delay::from_numer_denom_ms(delay_ms.round() as u32, 1),
+            )
+        })
+        .collect()
+}
+
+fn encode_gif(frames: Vec<Frame>) -> Result<Vec<u8>, image::ImageError> {
+    let mut buf = vec![];
+    {
+        let mut encoder = GifEncoder::new_with_speed(&mut buf, GIF_ENCODER_SPEED);
+        encoder.set_repeat(Repeat::Infinite)?;
+        encoder.encode_frames(frames)?;
+    }
+    Ok(buf)
+}
+
+fn encode(image: &DynamicImage, format: ImageOutputFormat) -> Result<Vec<u8>, image::ImageError> {
+    let mut buf = Cursor::new(vec![]);
+    image.write_to(&mut buf, format)?;
+    Ok(buf.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::AyameError;
+    use crate::model::temp::TempDir;
+    use image::{Rgba, RgbaImage};
+
+    // pseudo random pixels, so the png can't be compressed
+    fn noise(width: u32, height: u32, seed: u32) -> RgbaImage {
+        let mut state = seed;
+        RgbaImage::from_fn(width, height, |_, _| {
+            state = state.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+            let [r, g, b, _] = state.to_be_bytes();
+            Rgba([r, g, b, 255])
+        })
+    }
+
+    fn png(image: RgbaImage) -> Vec<u8> {
+        encode(&DynamicImage::ImageRgba8(image), ImageOutputFormat::Png).unwrap()
+    }
+
+    fn crc32(bytes: &[u8]) -> u32 {
+        let mut crc = !0u32;
+        for byte in bytes {
+            crc ^= *byte as u32;
+            for _ in 0..8 {
+                crc = if crc & 1 == 1 {
+                    (crc >> 1) ^ 0xEDB8_8320
+                } else {
+                    crc >> 1
+                };
+            }
+        }
+        !crc
+    }
+
+    // (type, data) of every chunk after the signature
+    fn chunks(png: &[u8]) -> Vec<([u8; 4], Vec<u8>)> {
+        let mut chunks = vec![];
+        let mut rest = &png[8..];
+        while rest.len() >= 12 {
+            let len = u32::from_be_bytes([rest[0], rest[1], rest[2], rest[3]]) as usize;
+            let kind = [rest[4], rest[5], rest[6], rest[7]];
+            chunks.push((kind, rest[8..8 + len].to_vec()));
+            rest = &rest[12 + len..];
+        }
+        chunks
+    }
+
+    fn assemble(chunks: &[([u8; 4], Vec<u8>)]) -> Vec<u8> {
+        let mut png = b"\x89PNG\r\n\x1a\n".to_vec();
+        for (kind, data) in chunks {
+            png.extend_from_slice(&(data.len() as u32).to_be_bytes());
+            let start = png.len();
+            png.extend_from_slice(kind);
+            png.extend_from_slice(data);
+            let crc = crc32(&png[start..]);
+            png.extend_from_slice(&crc.to_be_bytes());
+        }
+        png
+    }
+
+    fn image_data(png: &[u8]) -> Vec<u8> {
+        chunks(png)
+            .into_iter()
+            .filter(|(kind, _)| kind == b"IDAT")
+            .flat_map(|(_, data)| data)
+            .collect()
+    }
+
+    // the image crate can't encode apng, so the frames are put together by hand
+    fn apng(frames: Vec<RgbaImage>) -> Vec<u8> {
+        let (width, height) = frames[0].dimensions();
+        let encoded = frames.into_iter().map(png).collect::<Vec<_>>();
+        let header = chunks(&encoded[0]).remove(0);
+        let mut animation_control = (encoded.len() as u32).to_be_bytes().to_vec();
+        animation_control.extend_from_slice(&0u32.to_be_bytes());
+        let mut apng = vec![header, (*b"acTL", animation_control)];
+        let mut sequence = 0u32;
+        for (i, frame) in encoded.iter().enumerate() {
+            let mut frame_control = sequence.to_be_bytes().to_vec();
+            for value in [width, height, 0, 0] {
+                frame_control.extend_from_slice(&value.to_be_bytes());
+            }
+            // 1/10 s delay, no dispose and no blend
+            frame_control.extend_from_slice(&[0, 1, 0, 10, 0, 0]);
+            apng.push((*b"fcTL", frame_control));
+            sequence += 1;
+            if i == 0 {
+                apng.push((*b"IDAT", image_data(frame)));
+            } else {
+                let mut frame_data = sequence.to_be_bytes().to_vec();
+                frame_data.extend(image_data(frame));
+                apng.push((*b"fdAT", frame_data));
+                sequence += 1;
+            }
+        }
+        apng.push((*b"IEND", vec![]));
+        assemble(&apng)
+    }
+
+    #[test]
+    fn test_reduce_oversized_png() {
+        let dir = TempDir::new("test_reduce_oversized_png").unwrap();
+        let path = dir.file("emote.png");
+        let bytes = png(noise(512, 512, 1));
+        assert!(bytes.len() as u64 > MAX_EMOTE_SIZE);
+        fs::write(&path, &bytes).unwrap();
+
+        let reduced = reduce_emote_size(&path).unwrap();
+        assert!(reduced.size <= MAX_EMOTE_SIZE);
+        assert_eq!(fs::metadata(&reduced.path).unwrap().len(), reduced.size);
+        assert!(reduced.width <= MAX_EMOTE_DIMENSION && reduced.height <= MAX_EMOTE_DIMENSION);
+        assert!(!reduced.animated);
+    }
+
+    #[test]
+    fn test_apng_becomes_gif() {
+        let dir = TempDir::new("test_apng_becomes_gif").unwrap();
+        let path = dir.file("emote.png");
+        let bytes = apng(vec![noise(64, 64, 1), noise(64, 64, 2)]);
+        assert!(is_animated(&bytes));
+        fs::write(&path, &bytes).unwrap();
+
+        let reduced = reduce_emote_size(&path).unwrap();
+        assert!(reduced.animated);
+        assert_eq!(reduced.path.extension().unwrap(), "gif");
+        let gif = fs::read(&reduced.path).unwrap();
+        assert_eq!(image::guess_format(&gif).unwrap(), ImageFormat::Gif);
+        let frames = GifDecoder::new(Cursor::new(gif)).unwrap().into_frames();
+        assert_eq!(frames.count(), 2);
+    }
+
+    #[test]
+    fn test_too_many_pixels() {
+        let dir = TempDir::new("test_too_many_pixels").unwrap();
+        let path = dir.file("emote.png");
+        // only the header claims the huge size, it's rejected before the data gets decoded
+        let mut chunks = chunks(&png(noise(8, 8, 1)));
+        chunks[0].1[..8].copy_from_slice(&[0, 0, 0x13, 0x88, 0, 0, 0x13, 0x88]);
+        fs::write(&path, assemble(&chunks)).unwrap();
 
-pub fn reduce_emote_size<'a>(img: &'a PathBuf) -> Result<&'a PathBuf, Error> {
-    return Ok(&img);
+        match reduce_emote_size(&path) {
+            Err(AyameError::ImageProcessingError {
+                source: ImageProcessingError::TooManyPixels,
+            }) => {}
+            Err(why) => panic!("unexpected error: {:?}", why),
+            Ok(_) => panic!("the image wasn't rejected"),
+        }
+    }
 }
@@ -1 +1,3 @@
 pub(crate) mod image_processing;
+pub(crate) mod opus;
+pub(crate) mod temp;
@@ -0,0 +1,246 @@
+use std::collections::VecDeque;
+use std::io::{self, Read};
+use std::process::{Child, Command, Stdio};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use songbird::input::codec::OpusDecoderState;
+use songbird::input::error::Result as InputResult;
+use songbird::input::restartable::Restart;
+use songbird::input::{Codec, Container, Input, Metadata, Reader, Restartable};
+
+use crate::error::*;
+use crate::Error;
+
+// only streams that can be sent to discord without transcoding
+const OPUS_FORMAT: &str = "bestaudio[acodec=opus][asr=48000]";
+const OGG_MAGIC: &[u8] = b"OggS";
+
+/// creates a source that gets copied by ffmpeg instead of transcoded, fails if the
+/// url doesn't provide an opus stream
+pub async fn opus_source(url: &str) -> Result<Input, Error> {
+    let (_, info) = resolve(url).await?;
+    // the stream url expires after a few hours, so only the page url is kept and the
+    // stream is resolved again whenever ffmpeg gets (re)started
+    let restarter = OpusRestarter {
+        url: url.to_string(),
+        metadata: Metadata::from_ytdl_output(info),
+    };
+    Ok(Restartable::new(restarter, true).await?.into())
+}
+
+/// asks yt-dlp for the opus stream of the page, returns the stream url and the info
+async fn resolve(url: &str) -> Result<(String, serde_json::Value), Error> {
+    let output = tokio::process::Command::new("yt-dlp")
+        .arg("-j")
+        .arg("--no-playlist")
+        .arg("-f")
+        .arg(OPUS_FORMAT)
+        .arg(url)
+        .output()
+        .await?;
+    if !output.status.success() {
+        return Err(Error::Failure(NO_OPUS_STREAM));
+    }
+    let info: serde_json::Value =
+        serde_json::from_slice(&output.stdout).map_err(|_| Error::Failure(NO_OPUS_STREAM))?;
+    let stream_url = info["url"]
+        .as_str()
+        .ok_or_else(|| Error::Failure(NO_OPUS_STREAM))?
+        .to_string();
+    Ok((stream_url, info))
+}
+
+struct OpusRestarter {
+    url: String,
+    metadata: Metadata,
+}
+
+#[async_trait]
+impl Restart for OpusRestarter {
+    async fn call_restart(&mut self, time: Option<Duration>) -> InputResult<Input> {
+        let (stream_url, _) = resolve(&self.url)
+            .await
+            .map_err(|_| io::Error::new(io::ErrorKind::Other, NO_OPUS_STREAM))?;
+        let mut ffmpeg = Command::new("ffmpeg");
+        ffmpeg.args(&[
+            "-reconnect",
+            "1",
+            "-reconnect_streamed",
+            "1",
+            "-reconnect_delay_max",
+            "5",
+        ]);
+        if let Some(time) = time {
+            ffmpeg.arg("-ss").arg(format!("{:.3}", time.as_secs_f64()));
+        }
+        let child = ffmpeg
+            .arg("-i")
+            .arg(&stream_url)
+            .args(&[
+                "-vn",
+                "-c:a",
+                "copy",
+                "-f",
+                "ogg",
+                "-loglevel",
+                "error",
+                "pipe:1",
+            ])
+            .stdin(Stdio::null())
+            .stderr(Stdio::null())
+            .stdout(Stdio::piped())
+            .spawn()?;
+
+        Ok(Input::new(
+            true,
+            Reader::Extension(Box::new(OggOpusReader::from_child(child))),
+            Codec::Opus(OpusDecoderState::new()?),
+            Container::Dca { first_frame: 0 },
+            Some(self.metadata.clone()),
+        ))
+    }
+
+    async fn lazy_init(&mut self) -> InputResult<(Option<Metadata>, Codec, Container)> {
+        Ok((
+            Some(self.metadata.clone()),
+            Codec::Opus(OpusDecoderState::new()?),
+            Container::Dca { first_frame: 0 },
+        ))
+    }
+}
+
+/// demuxes an ogg opus stream into DCA frames (little endian `i16` length + opus packet)
+/// which songbird can pass through without decoding
+pub struct OggOpusReader<R: Read> {
+    inner: R,
+    child: Option<Child>,
+    // framed packets that are ready to be read
+    frames: VecDeque<u8>,
+    // packet that continues on the next page
+    partial: Vec<u8>,
+    packets: usize,
+}
+
+impl<R: Read> OggOpusReader<R> {
+    pub fn new(inner: R) -> Self {
+        Self {
+            inner,
+            child: None,
+            frames: VecDeque::new(),
+            partial: vec![],
+            packets: 0,
+        }
+    }
+
+    /// reads the next page, returns `false` when the stream ended
+    fn read_page(&mut self) -> io::Result<bool> {
+        let mut header = [0u8; 27];
+        match self.inner.read_exact(&mut header) {
+            Ok(()) => {}
+            Err(why) if why.kind() == io::ErrorKind::UnexpectedEof => return Ok(false),
+            Err(why) => return Err(why),
+        }
+        if &header[..4] != OGG_MAGIC {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "stream isn't ogg",
+            ));
+        }
+        let mut segments = vec![0u8; header[26] as usize];
+        self.inner.read_exact(&mut segments)?;
+        let mut body = vec![0u8; segments.iter().map(|s| *s as usize).sum()];
+        self.inner.read_exact(&mut body)?;
+
+        let mut offset = 0;
+        for lacing in segments {
+            let lacing = lacing as usize;
+            self.partial
+                .extend_from_slice(&body[offset..offset + lacing]);
+            offset += lacing;
+            // a lacing value below 255 finishes the packet
+            if lacing < 255 {
+                let packet = std::mem::take(&mut self.partial);
+                self.packets += 1;
+                // the first two packets are the OpusHead and OpusTags headers
+                if self.packets > 2 && !packet.is_empty() {
+                    self.frames
+                        .extend((packet.len() as i16).to_le_bytes().iter());
+                    self.frames.extend(packet);
+                }
+            }
+        }
+        Ok(true)
+    }
+}
+
+impl OggOpusReader<std::process::ChildStdout> {
+    pub fn from_child(mut child: Child) -> Self {
+        let stdout = child
+            .stdout
+            .take()
+            .expect("stdout of the child has to be piped");
+        let mut reader = Self::new(stdout);
+        reader.child = Some(child);
+        reader
+    }
+}
+
+impl<R: Read> Read for OggOpusReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.frames.is_empty() {
+            if !self.read_page()? {
+                return Ok(0);
+            }
+        }
+        let len = buf.len().min(self.frames.len());
+        for (dst, src) in buf.iter_mut().zip(self.frames.drain(..len)) {
+            *dst = src;
+        }
+        Ok(len)
+    }
+}
+
+impl<R: Read> Drop for OggOpusReader<R> {
+    fn drop(&mut self) {
+        if let Some(child) = &mut self.child {
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn page(segments: &[u8], body: &[u8]) -> Vec<u8> {
+        let mut page = OGG_MAGIC.to_vec();
+        // version, header type, granule, serial, sequence and crc aren't checked
+        page.extend_from_slice(&[0u8; 22]);
+        page.push(segments.len() as u8);
+        page.extend_from_slice(segments);
+        page.extend_from_slice(body);
+        page
+    }
+
+    #[test]
+    fn test_ogg_to_dca() {
+        let mut stream = page(&[8], b"OpusHead");
+        stream.extend(page(&[8], b"OpusTags"));
+        // packet of 300 bytes spanning two pages and a packet of 3 bytes
+        stream.extend(page(&[255], &[1u8; 255]));
+        stream.extend(page(&[45, 3], &[[1u8; 45].as_ref(), &[2u8; 3]].concat()));
+
+        let mut out = vec![];
+        OggOpusReader::new(stream.as_slice())
+            .read_to_end(&mut out)
+            .unwrap();
+
+        let mut expected = 300i16.to_le_bytes().to_vec();
+        expected.extend_from_slice(&[1u8; 300]);
+        expected.extend_from_slice(&3i16.to_le_bytes());
+        expected.extend_from_slice(&[2u8; 3]);
+        assert_eq!(out, expected);
+    }
+}
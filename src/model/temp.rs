@@ -0,0 +1,121 @@
+use std::fmt::Display;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use tracing::error;
+
+use crate::utils::bot_dir;
+
+const MAX_FILENAME_LEN: usize = 64;
+// no command keeps its temporary files that long
+const STALE_AFTER: Duration = Duration::from_secs(60 * 60);
+
+/// directory for files that only live as long as a command
+pub fn tmp_dir() -> PathBuf {
+    let mut dir = bot_dir();
+    dir.push("tmp");
+    dir
+}
+
+/// a directory in `tmp_dir()` that gets removed with everything in it when it's dropped
+pub struct TempDir {
+    path: PathBuf,
+}
+
+impl TempDir {
+    /// the name has to be unique while the directory lives, e.g. the id of the invocation
+    pub fn new(name: impl Display) -> io::Result<Self> {
+        let mut path = tmp_dir();
+        path.push(sanitize_filename(&name.to_string()));
+        fs::create_dir_all(&path)?;
+        Ok(Self { path })
+    }
+
+    /// path of a file in this directory, the name is sanitized so it can't leave the directory
+    pub fn file(&self, filename: &str) -> PathBuf {
+        self.path.join(sanitize_filename(filename))
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        if let Err(why) = fs::remove_dir_all(&self.path) {
+            error!("couldn't remove {}: {:?}", self.path.display(), why);
+        }
+    }
+}
+
+/// only keeps ascii alphanumerics, `.`, `-` and `_` and doesn't allow hidden files
+pub fn sanitize_filename(filename: &str) -> String {
+    let name = filename
+        .chars()
+        .map(|c| match c {
+            'a'..='z' | 'A'..='Z' | '0'..='9' | '.' | '-' | '_' => c,
+            _ => '_',
+        })
+        .collect::<String>();
+    let name = name.trim_start_matches('.');
+    // keep the end, it contains the extension
+    let skip = name.len().saturating_sub(MAX_FILENAME_LEN);
+    match &name[skip..] {
+        "" => String::from("file"),
+        name => name.to_string(),
+    }
+}
+
+/// removes everything in `tmp_dir()` that is older than `STALE_AFTER`, e.g. the files left
+/// after a crash. Files of commands that are still running are kept
+pub fn sweep_tmp_dir() -> io::Result<usize> {
+    let entries = match fs::read_dir(tmp_dir()) {
+        Ok(entries) => entries,
+        Err(why) if why.kind() == io::ErrorKind::NotFound => return Ok(0),
+        Err(why) => return Err(why),
+    };
+    let mut removed = 0;
+    for entry in entries {
+        let entry = entry?;
+        let fresh = entry
+            .metadata()?
+            .modified()?
+            .elapsed()
+            .map_or(true, |age| age < STALE_AFTER);
+        if fresh {
+            continue;
+        }
+        if entry.file_type()?.is_dir() {
+            fs::remove_dir_all(entry.path())?;
+        } else {
+            fs::remove_file(entry.path())?;
+        }
+        removed += 1;
+    }
+    Ok(removed)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::model::temp::*;
+
+    #[test]
+    fn test_sanitize_filename() {
+        assert_eq!(sanitize_filename("emote.png"), "emote.png");
+        assert_eq!(sanitize_filename("../../config.toml"), "_.._config.toml");
+        assert_eq!(sanitize_filename("dir/file\\name.gif"), "dir_file_name.gif");
+        assert_eq!(sanitize_filename(".hidden"), "hidden");
+        assert_eq!(sanitize_filename(""), "file");
+        assert_eq!(sanitize_filename("..."), "file");
+        let long = format!("{}.gif", "a".repeat(100));
+        assert_eq!(sanitize_filename(&long).len(), MAX_FILENAME_LEN);
+        assert!(sanitize_filename(&long).ends_with(".gif"));
+    }
+
+    #[test]
+    fn test_sweep_keeps_fresh_dirs() {
+        let dir = TempDir::new("test_sweep_keeps_fresh_dirs").unwrap();
+        fs::write(dir.file("emote.png"), b"png").unwrap();
+        sweep_tmp_dir().unwrap();
+        assert!(dir.file("emote.png").exists());
+    }
+}
@@ -1,16 +1,16 @@
 use chrono::Weekday;
 use core::fmt;
-use mensa_swfr_rs::mensa;
-use regex::Regex;
-use std::{fmt::Display, path::PathBuf};
+use std::{fmt::Display, path::PathBuf, time::Duration};
 
-use poise::{serenity::Result as SerenityResult, serenity_prelude::CreateEmbed};
+use poise::serenity::Result as SerenityResult;
+use poise::serenity_prelude as serenity;
 use rand::Rng;
 
 use tracing::error;
 
 use crate::commands::manage::get_bound_channel_id;
 use crate::error::*;
+use crate::voice;
 use crate::{Context, Error};
 
 pub fn bot_dir() -> PathBuf {
@@ -40,36 +40,6 @@ pub fn mock_text(text: &str) -> String {
     mock_str
 }
 
-pub fn create_mensa_plan_by_day(
-    day: &mensa::Day,
-) -> Result<CreateEmbed, Box<dyn std::error::Error>> {
-    let mut embed = CreateEmbed::default();
-    embed.title(format!(
-        "{} ({})",
-        weekday_german(&day.weekday()?),
-        day.to_chrono()?.format("%d.%m.%Y")
-    ));
-    for menu in day.menues.iter() {
-        let price = &menu.price;
-        embed.field(
-            &menu.art,
-            format!(
-                "{}\n\nZusatz: {}\n\nPreis: {}/{}/{}",
-                Regex::new(r"--+").unwrap().replace(&menu.name, "\n\n!!! "),
-                match &menu.food_type {
-                    Some(typ) => typ,
-                    None => "None",
-                },
-                price.price_students,
-                price.price_workers,
-                price.price_guests,
-            ),
-            false,
-        );
-    }
-    Ok(embed)
-}
-
 pub fn translate_weekday(wd: &str) -> String {
     match wd.to_lowercase().as_str() {
         "montag" | "mo" => format!("{}", Weekday::Mon),
@@ -134,6 +104,47 @@ pub(crate) async fn bind_command(ctx: Context<'_>) -> Result<bool, Error> {
     }
 }
 
+/// like `bind_command`, but only DJs or users that requested every track of the queue pass
+pub(crate) async fn dj_command(ctx: Context<'_>) -> Result<bool, Error> {
+    bind_command(ctx).await?;
+    let tracks = match voice::get_call(&ctx).await {
+        Ok(call) => call.lock().await.queue().current_queue(),
+        // there is nothing to protect
+        Err(_) => return Ok(true),
+    };
+    if voice::may_modify(&ctx, &tracks).await? {
+        Ok(true)
+    } else {
+        Err(Error::Input(ONLY_FOR_DJS))
+    }
+}
+
+/// like `bind_command`, but only DJs pass, for settings that affect every listener
+pub(crate) async fn dj_only_command(ctx: Context<'_>) -> Result<bool, Error> {
+    bind_command(ctx).await?;
+    if voice::is_dj(&ctx).await? {
+        Ok(true)
+    } else {
+        Err(Error::Input(ONLY_DJS))
+    }
+}
+
+/// posts the message in the bound channel, unless the command already was invoked there
+pub(crate) async fn log_to_bound_channel(ctx: &Context<'_>, text: &str) -> Result<(), Error> {
+    let guild_id = match ctx.guild_id() {
+        Some(id) => id.0 as i64,
+        None => return Ok(()),
+    };
+    if let Some(bind_id) = get_bound_channel_id(&ctx.data().database, guild_id).await? {
+        if bind_id != ctx.channel_id().0 {
+            serenity::ChannelId(bind_id)
+                .say(&ctx.discord().http, text)
+                .await?;
+        }
+    }
+    Ok(())
+}
+
 pub struct Bar {
     pub pos_icon: String,
 
@@ -182,3 +193,66 @@ impl Display for Bar {
         write!(fmt, "[{}{}{}]", prev, h, next)
     }
 }
+
+pub fn format_duration(duration: &Duration) -> String {
+    let secs = duration.as_secs();
+    if secs >= 3600 {
+        format!("{}:{:02}:{:02}", secs / 3600, (secs % 3600) / 60, secs % 60)
+    } else {
+        format!("{:02}:{:02}", secs / 60, secs % 60)
+    }
+}
+
+/// parses `90`, `1:30`, `1:02:30` or humantime like `1m 30s`
+pub fn parse_timestamp(timestamp: &str) -> Result<Duration, Error> {
+    let timestamp = timestamp.trim();
+    if timestamp.contains(':') {
+        let parts = timestamp
+            .split(':')
+            .map(|part| part.parse::<u64>())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| Error::Input(INVALID_TIMESTAMP))?;
+        let secs = match parts[..] {
+            [minutes, seconds] if seconds < 60 => minutes * 60 + seconds,
+            [hours, minutes, seconds] if minutes < 60 && seconds < 60 => {
+                hours * 3600 + minutes * 60 + seconds
+            }
+            _ => return Err(Error::Input(INVALID_TIMESTAMP)),
+        };
+        Ok(Duration::from_secs(secs))
+    } else if let Ok(secs) = timestamp.parse::<u64>() {
+        Ok(Duration::from_secs(secs))
+    } else {
+        humantime::parse_duration(timestamp).map_err(|_| Error::Input(INVALID_TIMESTAMP))
+    }
+}
+
+/// whether the query failed because of a `UNIQUE` constraint, e.g. when two users insert the
+/// same value at the same time
+pub fn is_unique_violation(why: &sqlx::Error) -> bool {
+    match why {
+        // the extended result codes of sqlite for unique and primary key constraints
+        sqlx::Error::Database(why) => matches!(why.code().as_deref(), Some("2067") | Some("1555")),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::utils::*;
+
+    #[test]
+    fn test_parse_timestamp() {
+        let secs = |timestamp| parse_timestamp(timestamp).ok().map(|d| d.as_secs());
+        assert_eq!(secs("1:30"), Some(90));
+        assert_eq!(secs("90"), Some(90));
+        assert_eq!(secs("1m 30s"), Some(90));
+        assert_eq!(secs("1:02:03"), Some(3723));
+        assert_eq!(secs(" 0:05 "), Some(5));
+        assert_eq!(secs("1:60"), None);
+        assert_eq!(secs("1:2:3:4"), None);
+        assert_eq!(secs("a:30"), None);
+        assert_eq!(secs("garbage"), None);
+        assert_eq!(secs(""), None);
+    }
+}
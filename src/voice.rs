@@ -0,0 +1,1409 @@
+use std::collections::{HashMap, HashSet};
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+use chrono::Utc;
+use once_cell::sync::Lazy;
+use poise::serenity_prelude::{self as serenity, CreateComponents, CreateEmbed, TypeMapKey};
+use songbird::input::{Input, Restartable};
+use songbird::tracks::{PlayMode, TrackHandle, TrackState};
+use songbird::{Call, Event, EventContext, EventHandler, Songbird, TrackEvent};
+use strum_macros::{Display, EnumString};
+use tokio::sync::Mutex;
+use tracing::{debug, error};
+
+use crate::commands::manage::{
+    get_bound_channel_id, get_dj_settings, get_status_msg, get_status_msgs, register_msg,
+    unregister_msg,
+};
+use crate::error::*;
+use crate::model::opus;
+use crate::utils::{check_result, check_result_ayame, format_duration, Bar};
+use crate::youtube::{Type, YoutubeSearch};
+use crate::{Context, Data, Error};
+
+// interval in which the progress bar of the status message gets updated
+const STATUS_UPDATE_INTERVAL: Duration = Duration::from_secs(15);
+
+// amount of resolved playlist entries after which the progress message gets updated
+const PLAYLIST_PROGRESS_STEP: usize = 5;
+
+// youtube generates lists with these id prefixes for mixes and the uploads of a channel
+const AUTO_GENERATED_LISTS: [&str; 2] = ["RD", "UL"];
+
+// how long the restore queue prompt waits for an answer
+const RESTORE_TIMEOUT: Duration = Duration::from_secs(600);
+
+// prevents that concurrent track events of a guild create multiple status messages,
+// one lock per guild so guilds don't wait on each other
+static STATUS_LOCKS: Lazy<Mutex<HashMap<serenity::GuildId, Arc<Mutex<()>>>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+// when the queue of a guild ran empty, shared by every idle watcher the guild ever had so
+// joining again doesn't leave old timers running
+static IDLE_SINCE: Lazy<Mutex<HashMap<serenity::GuildId, Instant>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+// search results autoplay picks the next track from
+const AUTOPLAY_CANDIDATES: u8 = 10;
+// videos autoplay remembers so it doesn't bounce between the same related videos
+const AUTOPLAY_HISTORY: usize = 20;
+
+// custom ids of the buttons under the status message
+pub const LOOP_BUTTON: &str = "status_loop";
+pub const AUTOPLAY_BUTTON: &str = "status_autoplay";
+
+#[derive(Display, EnumString, Clone, Copy, PartialEq)]
+#[strum(serialize_all = "lowercase")]
+pub enum LoopMode {
+    Off,
+    Track,
+    Queue,
+}
+
+impl LoopMode {
+    /// the mode the loop button switches to
+    pub fn next(self) -> Self {
+        match self {
+            LoopMode::Off => LoopMode::Track,
+            LoopMode::Track => LoopMode::Queue,
+            LoopMode::Queue => LoopMode::Off,
+        }
+    }
+}
+
+/// what happens when a track ends, stored per guild
+#[derive(Clone, Copy)]
+pub struct PlaybackMode {
+    pub loop_mode: LoopMode,
+    // plays related videos when the queue runs out
+    pub autoplay: bool,
+}
+
+impl Default for PlaybackMode {
+    fn default() -> Self {
+        Self {
+            loop_mode: LoopMode::Off,
+            autoplay: false,
+        }
+    }
+}
+
+/// key to store the user that requested a track in the typemap of the track
+pub struct Requester;
+
+impl TypeMapKey for Requester {
+    type Value = serenity::UserId;
+}
+
+pub async fn get_songbird(ctx: &serenity::Context) -> Result<Arc<Songbird>, Error> {
+    songbird::get(ctx)
+        .await
+        .ok_or_else(|| Error::Failure(FAILD_TO_GET_SONGBIRD))
+}
+
+/// joins the voice channel of the author of the command
+pub async fn join_author(ctx: &Context<'_>) -> Result<Arc<Mutex<Call>>, Error> {
+    let guild = ctx.guild().ok_or_else(|| Error::Input(NOT_IN_GUILD))?;
+    let channel_id = guild
+        .voice_states
+        .get(&ctx.author().id)
+        .and_then(|state| state.channel_id)
+        .ok_or_else(|| Error::Input(NOT_IN_VOICE))?;
+
+    join_channel(ctx.discord(), ctx.data(), guild.id, channel_id).await
+}
+
+pub async fn join_channel(
+    ctx: &serenity::Context,
+    data: &Data,
+    guild_id: serenity::GuildId,
+    channel_id: serenity::ChannelId,
+) -> Result<Arc<Mutex<Call>>, Error> {
+    let manager = get_songbird(ctx).await?;
+    let call = manager.join(guild_id, channel_id).await?;
+    register_events(&call, ctx, data, guild_id).await;
+    Ok(call)
+}
+
+/// registers the events that keep the status message and the saved queue of the guild
+/// up to date, that loop and autoplay tracks and that leave when nothing plays anymore
+async fn register_events(
+    call: &Arc<Mutex<Call>>,
+    ctx: &serenity::Context,
+    data: &Data,
+    guild_id: serenity::GuildId,
+) {
+    let mut call = call.lock().await;
+    // joining again (e.g. moving channels) shouldn't register the events twice
+    call.remove_all_global_events();
+    for event in [
+        Event::Track(TrackEvent::Play),
+        Event::Track(TrackEvent::Pause),
+        Event::Track(TrackEvent::End),
+        Event::Periodic(STATUS_UPDATE_INTERVAL, None),
+    ] {
+        call.add_global_event(
+            event,
+            StatusUpdater {
+                ctx: ctx.clone(),
+                data: data.clone(),
+                guild_id,
+            },
+        );
+    }
+    for event in [
+        Event::Track(TrackEvent::Play),
+        Event::Track(TrackEvent::End),
+    ] {
+        call.add_global_event(
+            event,
+            QueueSaver {
+                ctx: ctx.clone(),
+                data: data.clone(),
+                guild_id,
+            },
+        );
+    }
+    for event in [
+        Event::Track(TrackEvent::Play),
+        Event::Track(TrackEvent::End),
+    ] {
+        call.add_global_event(
+            event,
+            PlaybackHandler {
+                ctx: ctx.clone(),
+                data: data.clone(),
+                guild_id,
+            },
+        );
+    }
+    let idle_watcher = IdleWatcher {
+        ctx: ctx.clone(),
+        data: data.clone(),
+        guild_id,
+    };
+    call.add_global_event(Event::Track(TrackEvent::Play), idle_watcher.clone());
+    call.add_global_event(Event::Track(TrackEvent::End), idle_watcher.clone());
+    // the queue is empty after joining until something gets played
+    if call.queue().is_empty() {
+        idle_watcher.start().await;
+    }
+}
+
+/// gets the current call of the guild, fails if the bot isn't connected to voice
+pub async fn get_call(ctx: &Context<'_>) -> Result<Arc<Mutex<Call>>, Error> {
+    let guild_id = ctx.guild_id().ok_or_else(|| Error::Input(NOT_IN_GUILD))?;
+    get_songbird(ctx.discord())
+        .await?
+        .get(guild_id)
+        .ok_or_else(|| Error::Input(BOT_NOT_IN_VOICE))
+}
+
+/// gets the current call of the guild or joins the voice channel of the author
+pub async fn get_or_join_call(ctx: &Context<'_>) -> Result<Arc<Mutex<Call>>, Error> {
+    match get_call(ctx).await {
+        Ok(call) => Ok(call),
+        Err(_) => join_author(ctx).await,
+    }
+}
+
+/// creates a lazy source, plain text gets searched on youtube. With `copy_codec` opus
+/// streams get passed through without transcoding
+pub async fn create_source(query: &str, copy_codec: bool) -> Result<Input, Error> {
+    if copy_codec && is_url(query) {
+        match opus::opus_source(query).await {
+            Ok(source) => return Ok(source),
+            Err(why) => debug!("transcoding {}: {:?}", query, why),
+        }
+    }
+    let source = if is_url(query) {
+        Restartable::ytdl(query.to_string(), true).await?
+    } else {
+        Restartable::ytdl_search(query, true).await?
+    };
+    Ok(source.into())
+}
+
+/// returns the id of the youtube video of the url (`v=` or `youtu.be/`)
+pub fn video_id(query: &str) -> Option<String> {
+    let url = url::Url::parse(query).ok()?;
+    if url.host_str() == Some("youtu.be") {
+        return url
+            .path_segments()?
+            .next()
+            .filter(|id| !id.is_empty())
+            .map(String::from);
+    }
+    url.query_pairs()
+        .find(|(key, _)| key == "v")
+        .map(|(_, id)| id.to_string())
+}
+
+pub fn is_url(query: &str) -> bool {
+    url::Url::parse(query).is_ok()
+}
+
+/// returns the id of the playlist if the url contains one (`list=`)
+pub fn playlist_id(query: &str) -> Option<String> {
+    url::Url::parse(query)
+        .ok()?
+        .query_pairs()
+        .find(|(key, _)| key == "list")
+        .map(|(_, id)| id.to_string())
+        // mixes never end and are generated from the video of the url, so only the video is meant
+        .filter(|id| {
+            !AUTO_GENERATED_LISTS
+                .iter()
+                .any(|prefix| id.starts_with(prefix))
+        })
+}
+
+#[derive(Clone)]
+pub struct PlaylistEntry {
+    pub url: String,
+    pub title: Option<String>,
+}
+
+/// lists the videos of a youtube playlist without resolving the metadata of every video
+pub async fn playlist_entries(id: &str, limit: usize) -> Result<Vec<PlaylistEntry>, Error> {
+    let output = tokio::process::Command::new("yt-dlp")
+        .arg("--flat-playlist")
+        .arg("-J")
+        .arg("--playlist-end")
+        .arg(limit.to_string())
+        .arg(format!("https://www.youtube.com/playlist?list={}", id))
+        .output()
+        .await?;
+    if !output.status.success() {
+        return Err(Error::Failure(PLAYLIST_FAILED));
+    }
+    let playlist: serde_json::Value =
+        serde_json::from_slice(&output.stdout).map_err(|_| Error::Failure(PLAYLIST_FAILED))?;
+    Ok(playlist["entries"]
+        .as_array()
+        .map(|entries| {
+            entries
+                .iter()
+                .filter_map(|entry| {
+                    Some(PlaylistEntry {
+                        url: format!("https://www.youtube.com/watch?v={}", entry["id"].as_str()?),
+                        title: entry["title"].as_str().map(String::from),
+                    })
+                })
+                .take(limit)
+                .collect()
+        })
+        .unwrap_or_default())
+}
+
+/// resolves and enqueues the entries one after another in the background, the progress
+/// gets reported by editing `msg`
+pub fn spawn_import(
+    ctx: serenity::Context,
+    data: Data,
+    guild_id: serenity::GuildId,
+    call: Arc<Mutex<Call>>,
+    entries: Vec<(PlaylistEntry, serenity::UserId)>,
+    mut msg: serenity::Message,
+    title: &'static str,
+) {
+    tokio::spawn(async move {
+        let color = data.config.color().unwrap_or_default();
+        let total = entries.len();
+        let mut failed = 0;
+        let mut resolved = 0;
+        let mut stopped = false;
+        for (i, (entry, requester)) in entries.iter().enumerate() {
+            // the bot left the voice channel, no need to resolve the rest
+            if call.lock().await.current_channel().is_none() {
+                stopped = true;
+                break;
+            }
+            match create_source(&entry.url, data.config.copy_codec()).await {
+                Ok(source) => {
+                    enqueue(&call, source, *requester).await;
+                }
+                Err(why) => {
+                    failed += 1;
+                    error!(
+                        "couldn't resolve {} ({:?}): {:?}",
+                        entry.url, entry.title, why
+                    );
+                }
+            }
+            resolved = i + 1;
+            if resolved % PLAYLIST_PROGRESS_STEP == 0 && resolved < total {
+                let embed = import_progress_embed(title, resolved, total, failed, false, color);
+                check_result(msg.edit(&ctx, |m| m.set_embed(embed)).await);
+            }
+        }
+        // always shows the final state, the message would look stuck otherwise
+        let embed = import_progress_embed(title, resolved, total, failed, stopped, color);
+        check_result(msg.edit(&ctx, |m| m.set_embed(embed)).await);
+        check_result_ayame(save_queue(&data.database, guild_id, &call).await);
+    });
+}
+
+pub fn import_progress_embed(
+    title: &str,
+    resolved: usize,
+    total: usize,
+    failed: usize,
+    stopped: bool,
+    color: serenity::Color,
+) -> CreateEmbed {
+    let mut bar = Bar::default();
+    bar.set(resolved as f64 / total.max(1) as f64).set_len(25);
+    let mut embed = CreateEmbed::default();
+    embed
+        .title(if stopped {
+            format!("{} stopped", title)
+        } else if resolved < total {
+            format!("{}...", title)
+        } else {
+            String::from(title)
+        })
+        .color(color)
+        .description(format!("`{}` {}/{}", bar, resolved, total));
+    let mut notes = vec![];
+    if failed > 0 {
+        notes.push(format!("{} tracks couldn't be resolved", failed));
+    }
+    if stopped {
+        notes.push(String::from("the bot left the voice channel"));
+    }
+    if !notes.is_empty() {
+        embed.footer(|f| f.text(notes.join(", ")));
+    }
+    embed
+}
+
+/// enqueues the source in the builtin queue and remembers who requested it
+pub async fn enqueue(
+    call: &Arc<Mutex<Call>>,
+    source: Input,
+    requester: serenity::UserId,
+) -> TrackHandle {
+    let (track, handle) = songbird::create_player(source);
+    {
+        let mut typemap = handle.typemap().write().await;
+        typemap.insert::<Requester>(requester);
+        typemap.insert::<AddedAt>(Utc::now().timestamp());
+    }
+    call.lock().await.enqueue(track);
+    handle
+}
+
+pub async fn get_requester(handle: &TrackHandle) -> Option<serenity::UserId> {
+    handle.typemap().read().await.get::<Requester>().copied()
+}
+
+pub async fn get_added_at(handle: &TrackHandle) -> Option<i64> {
+    handle.typemap().read().await.get::<AddedAt>().copied()
+}
+
+/// the users in the voice channel of the bot, without bots
+pub fn listeners(ctx: &serenity::Context, guild_id: serenity::GuildId) -> Vec<serenity::UserId> {
+    let guild = match ctx.cache.guild(guild_id) {
+        Some(guild) => guild,
+        None => return vec![],
+    };
+    let channel_id = match bot_channel(ctx, guild_id) {
+        Some(channel_id) => channel_id,
+        None => return vec![],
+    };
+    guild
+        .voice_states
+        .values()
+        .filter(|state| state.channel_id == Some(channel_id))
+        .filter(|state| state.user_id != ctx.cache.current_user_id())
+        .filter(|state| {
+            // the member cache is usually empty without the members intent, voice states
+            // carry the member though
+            let bot = state
+                .member
+                .as_ref()
+                .map(|member| member.user.bot)
+                .or_else(|| ctx.cache.user(state.user_id).map(|user| user.bot))
+                .or_else(|| {
+                    guild
+                        .members
+                        .get(&state.user_id)
+                        .map(|member| member.user.bot)
+                });
+            !bot.unwrap_or(false)
+        })
+        .map(|state| state.user_id)
+        .collect()
+}
+
+/// the voice channel the bot is connected to according to the cache
+fn bot_channel(
+    ctx: &serenity::Context,
+    guild_id: serenity::GuildId,
+) -> Option<serenity::ChannelId> {
+    ctx.cache.guild(guild_id).and_then(|guild| {
+        guild
+            .voice_states
+            .get(&ctx.cache.current_user_id())
+            .and_then(|state| state.channel_id)
+    })
+}
+
+/// DJs can do everything with the queue. Without a DJ role everyone is a DJ, admins and users
+/// that listen alone always are
+pub async fn is_dj(ctx: &Context<'_>) -> Result<bool, Error> {
+    let guild_id = ctx.guild_id().ok_or_else(|| Error::Input(NOT_IN_GUILD))?;
+    is_dj_member(ctx.discord(), ctx.data(), guild_id, ctx.author().id).await
+}
+
+/// like `is_dj`, for interactions that don't come from a command
+pub async fn is_dj_member(
+    ctx: &serenity::Context,
+    data: &Data,
+    guild_id: serenity::GuildId,
+    user_id: serenity::UserId,
+) -> Result<bool, Error> {
+    let role_id = match get_dj_settings(&data.database, guild_id.0 as i64)
+        .await?
+        .role_id
+    {
+        Some(role_id) => role_id,
+        None => return Ok(true),
+    };
+    let member = guild_id.member(ctx, user_id).await?;
+    if member.roles.contains(&role_id)
+        || member
+            .permissions(ctx)?
+            .contains(serenity::Permissions::MANAGE_GUILD)
+    {
+        return Ok(true);
+    }
+    Ok(listeners(ctx, guild_id) == [user_id])
+}
+
+/// whether the author can remove or move the tracks, DJs can change every track and everyone
+/// else only their own
+pub async fn may_modify(ctx: &Context<'_>, tracks: &[TrackHandle]) -> Result<bool, Error> {
+    if is_dj(ctx).await? {
+        return Ok(true);
+    }
+    for track in tracks {
+        if get_requester(track).await != Some(ctx.author().id) {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
+/// adds the vote of the user and returns how many of the listeners voted to skip the track
+pub async fn add_skip_vote(
+    handle: &TrackHandle,
+    user_id: serenity::UserId,
+    listeners: &[serenity::UserId],
+) -> Result<usize, Error> {
+    let mut typemap = handle.typemap().write().await;
+    let votes = typemap.entry::<SkipVotes>().or_insert_with(HashSet::new);
+    if !votes.insert(user_id) {
+        return Err(Error::Input(ALREADY_VOTED));
+    }
+    // listeners that left don't count anymore
+    Ok(votes.iter().filter(|id| listeners.contains(id)).count())
+}
+
+/// moves the current track to the position `target` returns for the current position and
+/// refreshes the status message right away instead of waiting for the next periodic update
+pub async fn seek_current<F>(ctx: &Context<'_>, target: F) -> Result<Duration, Error>
+where
+    F: FnOnce(Duration) -> Duration,
+{
+    let guild_id = ctx.guild_id().ok_or_else(|| Error::Input(NOT_IN_GUILD))?;
+    let handle = get_call(ctx)
+        .await?
+        .lock()
+        .await
+        .queue()
+        .current()
+        .ok_or_else(|| Error::Input(NOTHING_PLAYING))?;
+    if !handle.is_seekable() {
+        return Err(Error::Input(NOT_SEEKABLE));
+    }
+    let position = target(handle.get_info().await?.position);
+    if let Some(duration) = handle.metadata().duration {
+        if position >= duration {
+            return Err(Error::Input(SEEK_OUT_OF_RANGE));
+        }
+    }
+    handle.seek_time(position)?;
+    // the seek is applied asynchronously, so the track info would still show the old position
+    refresh_status_message(ctx.discord(), ctx.data(), guild_id, Some(position)).await?;
+    Ok(position)
+}
+
+/// key to store the users that voted to skip the track
+pub struct SkipVotes;
+
+impl TypeMapKey for SkipVotes {
+    type Value = HashSet<serenity::UserId>;
+}
+
+/// key to store the videos autoplay already played in the track it picked
+pub struct AutoplayHistory;
+
+impl TypeMapKey for AutoplayHistory {
+    type Value = Vec<String>;
+}
+
+/// key to store the unix timestamp of the moment the track was added to the queue
+pub struct AddedAt;
+
+impl TypeMapKey for AddedAt {
+    type Value = i64;
+}
+
+struct QueueSaver {
+    ctx: serenity::Context,
+    data: Data,
+    guild_id: serenity::GuildId,
+}
+
+#[async_trait]
+impl EventHandler for QueueSaver {
+    async fn act(&self, _ctx: &EventContext<'_>) -> Option<Event> {
+        if let Ok(manager) = get_songbird(&self.ctx).await {
+            if let Some(call) = manager.get(self.guild_id) {
+                check_result_ayame(save_queue(&self.data.database, self.guild_id, &call).await);
+            }
+        }
+        None
+    }
+}
+
+/// loops tracks and the queue and keeps the music going with autoplay
+#[derive(Clone)]
+struct PlaybackHandler {
+    ctx: serenity::Context,
+    data: Data,
+    guild_id: serenity::GuildId,
+}
+
+#[async_trait]
+impl EventHandler for PlaybackHandler {
+    async fn act(&self, ctx: &EventContext<'_>) -> Option<Event> {
+        if let EventContext::Track(tracks) = ctx {
+            for (state, handle) in tracks.iter() {
+                check_result_ayame(self.handle_track(state, handle).await);
+            }
+        }
+        None
+    }
+}
+
+impl PlaybackHandler {
+    async fn handle_track(&self, state: &TrackState, handle: &TrackHandle) -> Result<(), Error> {
+        let mode = get_playback_mode(&self.data.database, self.guild_id.0 as i64).await?;
+        match state.playing {
+            PlayMode::Play => apply_loop_mode(handle, mode.loop_mode),
+            PlayMode::End | PlayMode::Stop => self.track_ended(state, handle, mode).await?,
+            _ => {}
+        }
+        Ok(())
+    }
+
+    async fn track_ended(
+        &self,
+        state: &TrackState,
+        handle: &TrackHandle,
+        mode: PlaybackMode,
+    ) -> Result<(), Error> {
+        let call = match get_songbird(&self.ctx).await?.get(self.guild_id) {
+            Some(call) => call,
+            // the bot left, nothing to continue
+            None => return Ok(()),
+        };
+        let url = match handle.metadata().source_url.clone() {
+            Some(url) => url,
+            None => return Ok(()),
+        };
+        // the queue could still contain the ended track
+        let remaining = call
+            .lock()
+            .await
+            .queue()
+            .current_queue()
+            .iter()
+            .filter(|queued| queued.uuid() != handle.uuid())
+            .count();
+        // skipped tracks stop too, but `stop` clears the queue before, so nothing gets requeued
+        let finished = state.playing == PlayMode::End;
+        let requester = get_requester(handle)
+            .await
+            .unwrap_or_else(|| self.ctx.cache.current_user_id());
+        // resolving takes a while and would hold up the other events of the call, so the next
+        // track gets resolved in the background
+        let handler = self.clone();
+        if mode.loop_mode == LoopMode::Queue && (finished || remaining > 0) {
+            tokio::spawn(async move {
+                check_result_ayame(handler.requeue(&call, &url, requester).await);
+            });
+        } else if mode.autoplay && finished && remaining == 0 {
+            let history = handle
+                .typemap()
+                .read()
+                .await
+                .get::<AutoplayHistory>()
+                .cloned()
+                .unwrap_or_default();
+            let last = handle.clone();
+            tokio::spawn(async move {
+                check_result_ayame(handler.autoplay(&call, &last, history).await);
+            });
+        }
+        Ok(())
+    }
+
+    /// puts the track at the end of the queue again
+    async fn requeue(
+        &self,
+        call: &Arc<Mutex<Call>>,
+        url: &str,
+        requester: serenity::UserId,
+    ) -> Result<(), Error> {
+        let source = create_source(url, self.data.config.copy_codec()).await?;
+        enqueue(call, source, requester).await;
+        Ok(())
+    }
+
+    /// enqueues a video similar to the last track, that wasn't played recently. Youtube dropped
+    /// related videos from its api, so it searches for the title and channel of the last track
+    async fn autoplay(
+        &self,
+        call: &Arc<Mutex<Call>>,
+        last: &TrackHandle,
+        mut history: Vec<String>,
+    ) -> Result<(), Error> {
+        let metadata = last.metadata();
+        let title = match &metadata.title {
+            Some(title) => title.clone(),
+            // nothing to search for
+            None => return Ok(()),
+        };
+        if let Some(id) = metadata.source_url.as_deref().and_then(video_id) {
+            history.push(id);
+        }
+        let query = match metadata
+            .channel
+            .as_ref()
+            .or_else(|| metadata.artist.as_ref())
+        {
+            Some(channel) => format!("{} {}", title, channel),
+            None => title.clone(),
+        };
+        let mut search = YoutubeSearch::new(self.data.config.youtube_api_key());
+        search
+            .set_filter(Type::VIDEO)
+            .set_amount(AUTOPLAY_CANDIDATES);
+        let response = match search.search(&query).await {
+            Ok(response) => response,
+            Err(why) => {
+                self.notify(&format!(
+                    "autoplay couldn't search for a track after `{}`",
+                    title
+                ))
+                .await?;
+                return Err(why);
+            }
+        };
+        let next = response
+            .results()
+            .iter()
+            .find(|result| video_id(&result.url()).map_or(false, |id| !history.contains(&id)));
+        let next = match next {
+            Some(next) => next.url(),
+            None => {
+                return self
+                    .notify(&format!("autoplay found nothing to play after `{}`", title))
+                    .await
+            }
+        };
+        let skip = history.len().saturating_sub(AUTOPLAY_HISTORY);
+        history.drain(..skip);
+        let source = create_source(&next, self.data.config.copy_codec()).await?;
+        let handle = enqueue(call, source, self.ctx.cache.current_user_id()).await;
+        handle
+            .typemap()
+            .write()
+            .await
+            .insert::<AutoplayHistory>(history);
+        Ok(())
+    }
+
+    /// tells the listeners in the bound channel why the music stopped
+    async fn notify(&self, text: &str) -> Result<(), Error> {
+        if let Some(channel_id) =
+            get_bound_channel_id(&self.data.database, self.guild_id.0 as i64).await?
+        {
+            serenity::ChannelId(channel_id)
+                .say(&self.ctx.http, text)
+                .await?;
+        }
+        Ok(())
+    }
+}
+
+/// leaves the voice channel after the queue was empty for the idle timeout of the config
+#[derive(Clone)]
+struct IdleWatcher {
+    ctx: serenity::Context,
+    data: Data,
+    guild_id: serenity::GuildId,
+}
+
+#[async_trait]
+impl EventHandler for IdleWatcher {
+    async fn act(&self, ctx: &EventContext<'_>) -> Option<Event> {
+        if let EventContext::Track(tracks) = ctx {
+            if tracks
+                .iter()
+                .any(|(state, _)| state.playing == PlayMode::Play)
+            {
+                IDLE_SINCE.lock().await.remove(&self.guild_id);
+            } else {
+                check_result_ayame(self.track_ended(tracks).await);
+            }
+        }
+        None
+    }
+}
+
+impl IdleWatcher {
+    async fn track_ended(&self, ended: &[(&TrackState, &TrackHandle)]) -> Result<(), Error> {
+        let call = match get_songbird(&self.ctx).await?.get(self.guild_id) {
+            Some(call) => call,
+            None => return Ok(()),
+        };
+        // the queue could still contain the ended tracks
+        let remaining = call
+            .lock()
+            .await
+            .queue()
+            .current_queue()
+            .iter()
+            .filter(|queued| {
+                !ended
+                    .iter()
+                    .any(|(_, handle)| handle.uuid() == queued.uuid())
+            })
+            .count();
+        if remaining == 0 {
+            self.start().await;
+        }
+        Ok(())
+    }
+
+    /// leaves after the idle timeout, unless something gets played in the meantime
+    async fn start(&self) {
+        let timeout = match self.data.config.idle_timeout() {
+            Some(timeout) => timeout,
+            None => return,
+        };
+        let started = Instant::now();
+        IDLE_SINCE.lock().await.insert(self.guild_id, started);
+        let watcher = self.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(timeout).await;
+            // something played or the queue ran empty again later
+            if IDLE_SINCE.lock().await.get(&watcher.guild_id) != Some(&started) {
+                return;
+            }
+            let call = match get_songbird(&watcher.ctx).await {
+                Ok(manager) => manager.get(watcher.guild_id),
+                Err(_) => None,
+            };
+            // autoplay or a loop could've queued a track that didn't start yet
+            if let Some(call) = call {
+                if call.lock().await.queue().is_empty() {
+                    check_result_ayame(
+                        disconnect(
+                            &watcher.ctx,
+                            &watcher.data,
+                            watcher.guild_id,
+                            &format!("nothing played for {}", humantime::format_duration(timeout)),
+                        )
+                        .await,
+                    );
+                }
+            }
+        });
+    }
+}
+
+/// leaves once every human left the voice channel of the bot
+pub async fn handle_voice_state_update(
+    ctx: &serenity::Context,
+    data: &Data,
+    state: &serenity::VoiceState,
+) -> Result<(), Error> {
+    let guild_id = match state.guild_id {
+        Some(guild_id) => guild_id,
+        None => return Ok(()),
+    };
+    if get_songbird(ctx).await?.get(guild_id).is_none() {
+        return Ok(());
+    }
+    if state.user_id == ctx.cache.current_user_id() {
+        // someone disconnected the bot, the call is useless now
+        if state.channel_id.is_none() {
+            leave_if_connected(ctx, &data.database, guild_id).await?;
+        }
+        return Ok(());
+    }
+    // without its own voice state the bot can't tell who is listening
+    if bot_channel(ctx, guild_id).is_some() && listeners(ctx, guild_id).is_empty() {
+        disconnect(ctx, data, guild_id, "everyone left").await?;
+    }
+    Ok(())
+}
+
+/// stops playing, leaves the voice channel and deletes the status message
+pub async fn leave(
+    ctx: &serenity::Context,
+    database: &sqlx::SqlitePool,
+    guild_id: serenity::GuildId,
+) -> Result<(), Error> {
+    if leave_if_connected(ctx, database, guild_id).await? {
+        Ok(())
+    } else {
+        Err(Error::Input(BOT_NOT_IN_VOICE))
+    }
+}
+
+/// like [`leave`] but does nothing if the bot isn't connected, returns whether it left
+async fn leave_if_connected(
+    ctx: &serenity::Context,
+    database: &sqlx::SqlitePool,
+    guild_id: serenity::GuildId,
+) -> Result<bool, Error> {
+    let manager = get_songbird(ctx).await?;
+    let call = match manager.get(guild_id) {
+        Some(call) => call,
+        None => return Ok(false),
+    };
+    call.lock().await.queue().stop();
+    IDLE_SINCE.lock().await.remove(&guild_id);
+    match manager.remove(guild_id).await {
+        Ok(()) => {}
+        // another event left in the meantime
+        Err(songbird::error::JoinError::NoCall) => return Ok(false),
+        Err(why) => return Err(why.into()),
+    }
+    delete_status_message(ctx, database, guild_id).await?;
+    Ok(true)
+}
+
+/// leaves on its own and says goodbye in the bound channel, does nothing if the bot already left
+async fn disconnect(
+    ctx: &serenity::Context,
+    data: &Data,
+    guild_id: serenity::GuildId,
+    reason: &str,
+) -> Result<(), Error> {
+    if !leave_if_connected(ctx, &data.database, guild_id).await? {
+        return Ok(());
+    }
+    if let Some(channel_id) = get_bound_channel_id(&data.database, guild_id.0 as i64).await? {
+        serenity::ChannelId(channel_id)
+            .say(&ctx.http, format!("left the voice channel, {}", reason))
+            .await?;
+    }
+    Ok(())
+}
+
+struct StatusUpdater {
+    ctx: serenity::Context,
+    data: Data,
+    guild_id: serenity::GuildId,
+}
+
+#[async_trait]
+impl EventHandler for StatusUpdater {
+    async fn act(&self, _ctx: &EventContext<'_>) -> Option<Event> {
+        check_result_ayame(update_status_message(&self.ctx, &self.data, self.guild_id).await);
+        None
+    }
+}
+
+/// edits the status message of the guild in place or creates a new one if there is none
+/// (or it got deleted). Deletes the status message if nothing is playing anymore.
+pub async fn update_status_message(
+    ctx: &serenity::Context,
+    data: &Data,
+    guild_id: serenity::GuildId,
+) -> Result<(), Error> {
+    refresh_status_message(ctx, data, guild_id, None).await
+}
+
+/// like [`update_status_message`] but shows `position` instead of the position in the track info
+async fn refresh_status_message(
+    ctx: &serenity::Context,
+    data: &Data,
+    guild_id: serenity::GuildId,
+    position: Option<Duration>,
+) -> Result<(), Error> {
+    let lock = STATUS_LOCKS
+        .lock()
+        .await
+        .entry(guild_id)
+        .or_default()
+        .clone();
+    let _lock = lock.lock().await;
+    let database = &data.database;
+    let id = guild_id.0 as i64;
+    let channel_id = match get_bound_channel_id(database, id).await? {
+        Some(channel_id) => serenity::ChannelId(channel_id),
+        None => return Ok(()),
+    };
+    let current = match get_songbird(ctx).await?.get(guild_id) {
+        Some(call) => call.lock().await.queue().current(),
+        None => None,
+    };
+    let handle = match current {
+        Some(handle) => handle,
+        None => return delete_status_message(ctx, database, guild_id).await,
+    };
+    let embed = create_status_embed(data, &handle, position).await?;
+    let components = status_components(get_playback_mode(database, id).await?);
+
+    if let Some(msg_id) = get_status_msg(database, id).await? {
+        match channel_id
+            .edit_message(&ctx.http, msg_id, |m| {
+                m.set_embed(embed.clone()).components(|c| {
+                    c.clone_from(&components);
+                    c
+                })
+            })
+            .await
+        {
+            Ok(_) => return Ok(()),
+            Err(serenity::Error::Http(why))
+                if why.status_code().map(|c| c.as_u16()) == Some(404) =>
+            {
+                unregister_msg(database, id, msg_id as i64).await?
+            }
+            Err(why) => return Err(why.into()),
+        }
+    }
+    let msg = channel_id
+        .send_message(&ctx.http, |m| {
+            m.set_embed(embed).components(|c| {
+                c.clone_from(&components);
+                c
+            })
+        })
+        .await?;
+    register_msg(database, id, msg.id.0 as i64).await?;
+    Ok(())
+}
+
+pub async fn delete_status_message(
+    ctx: &serenity::Context,
+    database: &sqlx::SqlitePool,
+    guild_id: serenity::GuildId,
+) -> Result<(), Error> {
+    let id = guild_id.0 as i64;
+    if let Some(msg_id) = get_status_msg(database, id).await? {
+        if let Some(channel_id) = get_bound_channel_id(database, id).await? {
+            // message could be deleted already
+            let _ = serenity::ChannelId(channel_id)
+                .delete_message(&ctx.http, msg_id)
+                .await;
+        }
+        unregister_msg(database, id, msg_id as i64).await?;
+    }
+    Ok(())
+}
+
+/// deletes all status messages that are left over from the last run
+pub async fn cleanup_status_messages(
+    ctx: &serenity::Context,
+    database: &sqlx::SqlitePool,
+) -> Result<(), Error> {
+    for (guild_id, msg_id) in get_status_msgs(database).await? {
+        if let Some(channel_id) = get_bound_channel_id(database, guild_id).await? {
+            let _ = serenity::ChannelId(channel_id)
+                .delete_message(&ctx.http, msg_id as u64)
+                .await;
+        }
+        unregister_msg(database, guild_id, msg_id).await?;
+    }
+    Ok(())
+}
+
+async fn create_status_embed(
+    data: &Data,
+    handle: &TrackHandle,
+    position: Option<Duration>,
+) -> Result<CreateEmbed, Error> {
+    let metadata = handle.metadata();
+    let title = metadata
+        .title
+        .clone()
+        .unwrap_or_else(|| String::from("unknown title"));
+    let position = match position {
+        Some(position) => position,
+        None => handle.get_info().await?.position,
+    };
+
+    let mut embed = CreateEmbed::default();
+    embed.title("Now playing").color(data.config.color()?);
+    match &metadata.source_url {
+        Some(url) => embed.description(format!("[{}]({})", title, url)),
+        None => embed.description(title),
+    };
+    if let Some(thumbnail) = &metadata.thumbnail {
+        embed.thumbnail(thumbnail);
+    }
+    if let Some(requester) = get_requester(handle).await {
+        embed.field("Requested by", format!("<@{}>", requester), true);
+    }
+    match metadata.duration {
+        Some(duration) => {
+            let mut bar = Bar::default();
+            bar.set(position.as_secs_f64() / duration.as_secs_f64().max(1.0))
+                .set_len(25);
+            embed.field(
+                "Progress",
+                format!(
+                    "`{}` {}/{}",
+                    bar,
+                    format_duration(&position),
+                    format_duration(&duration)
+                ),
+                false,
+            )
+        }
+        None => embed.field("Progress", format_duration(&position), false),
+    };
+    Ok(embed)
+}
+
+/// the buttons under the status message that switch the playback mode
+fn status_components(mode: PlaybackMode) -> CreateComponents {
+    let mut components = CreateComponents::default();
+    components.create_action_row(|row| {
+        row.create_button(|b| {
+            b.custom_id(LOOP_BUTTON)
+                .label(format!("loop: {}", mode.loop_mode))
+                .style(if mode.loop_mode == LoopMode::Off {
+                    serenity::ButtonStyle::Secondary
+                } else {
+                    serenity::ButtonStyle::Primary
+                })
+        })
+        .create_button(|b| {
+            b.custom_id(AUTOPLAY_BUTTON)
+                .label("autoplay")
+                .style(if mode.autoplay {
+                    serenity::ButtonStyle::Success
+                } else {
+                    serenity::ButtonStyle::Secondary
+                })
+        })
+    });
+    components
+}
+
+/// switches the playback mode with the buttons under the status message
+pub async fn handle_status_button(
+    ctx: &serenity::Context,
+    data: &Data,
+    mci: &serenity::MessageComponentInteraction,
+) -> Result<(), Error> {
+    let guild_id = match mci.guild_id {
+        Some(guild_id) => guild_id,
+        None => return Ok(()),
+    };
+    let mut mode = get_playback_mode(&data.database, guild_id.0 as i64).await?;
+    match mci.data.custom_id.as_str() {
+        LOOP_BUTTON => mode.loop_mode = mode.loop_mode.next(),
+        AUTOPLAY_BUTTON => mode.autoplay = !mode.autoplay,
+        // belongs to a menu
+        _ => return Ok(()),
+    }
+    // the same rules as for the `loop` and `autoplay` commands
+    let denied = if get_bound_channel_id(&data.database, guild_id.0 as i64).await?
+        != Some(mci.channel_id.0)
+    {
+        Some(ONLY_IN_BOT_CHANNEL)
+    } else if !is_dj_member(ctx, data, guild_id, mci.user.id).await? {
+        Some(ONLY_DJS)
+    } else {
+        None
+    };
+    if let Some(why) = denied {
+        mci.create_interaction_response(&ctx.http, |r| {
+            r.kind(serenity::InteractionResponseType::ChannelMessageWithSource)
+                .interaction_response_data(|d| d.content(why).ephemeral(true))
+        })
+        .await?;
+        return Ok(());
+    }
+    mci.create_interaction_response(&ctx.http, |r| {
+        r.kind(serenity::InteractionResponseType::DeferredUpdateMessage)
+    })
+    .await?;
+    change_playback_mode(ctx, data, guild_id, mode).await
+}
+
+/// saves the playback mode, applies it to the current track and shows it in the status message
+pub async fn change_playback_mode(
+    ctx: &serenity::Context,
+    data: &Data,
+    guild_id: serenity::GuildId,
+    mode: PlaybackMode,
+) -> Result<(), Error> {
+    set_playback_mode(&data.database, guild_id.0 as i64, mode).await?;
+    if let Some(call) = get_songbird(ctx).await?.get(guild_id) {
+        if let Some(current) = call.lock().await.queue().current() {
+            apply_loop_mode(&current, mode.loop_mode);
+        }
+    }
+    update_status_message(ctx, data, guild_id).await
+}
+
+/// only the track mode loops a single track, the queue mode requeues tracks when they end
+fn apply_loop_mode(handle: &TrackHandle, loop_mode: LoopMode) {
+    // fails for tracks that can't be looped, they just play once
+    let _ = match loop_mode {
+        LoopMode::Track => handle.enable_loop(),
+        _ => handle.disable_loop(),
+    };
+}
+
+pub async fn get_playback_mode(
+    database: &sqlx::SqlitePool,
+    guild_id: i64,
+) -> Result<PlaybackMode, Error> {
+    Ok(sqlx::query!(
+        "SELECT loop_mode, autoplay FROM guild_playback WHERE guild_id = ?",
+        guild_id
+    )
+    .fetch_optional(database)
+    .await?
+    .map_or_else(PlaybackMode::default, |entry| PlaybackMode {
+        loop_mode: LoopMode::from_str(&entry.loop_mode).unwrap_or(LoopMode::Off),
+        autoplay: entry.autoplay,
+    }))
+}
+
+pub async fn set_playback_mode(
+    database: &sqlx::SqlitePool,
+    guild_id: i64,
+    mode: PlaybackMode,
+) -> Result<(), Error> {
+    let loop_mode = mode.loop_mode.to_string();
+    sqlx::query!(
+        "INSERT OR REPLACE INTO guild_playback (guild_id, loop_mode, autoplay) VALUES (?, ?, ?)",
+        guild_id,
+        loop_mode,
+        mode.autoplay
+    )
+    .execute(database)
+    .await?;
+    Ok(())
+}
+
+/// replaces the saved queue of the guild with the current queue
+pub async fn save_queue(
+    database: &sqlx::SqlitePool,
+    guild_id: serenity::GuildId,
+    call: &Arc<Mutex<Call>>,
+) -> Result<(), Error> {
+    let id = guild_id.0 as i64;
+    let tracks = call.lock().await.queue().current_queue();
+    let mut tx = database.begin().await?;
+    sqlx::query!("DELETE FROM queue_entry WHERE guild_id = ?", id)
+        .execute(&mut tx)
+        .await?;
+    for (position, handle) in tracks.iter().enumerate() {
+        let metadata = handle.metadata();
+        let url = match &metadata.source_url {
+            Some(url) => url,
+            // can't be restored anyway
+            None => continue,
+        };
+        let position = position as i64;
+        let requester_id = get_requester(handle)
+            .await
+            .map(|id| id.0 as i64)
+            .unwrap_or(0);
+        let added_at = get_added_at(handle)
+            .await
+            .unwrap_or_else(|| Utc::now().timestamp());
+        sqlx::query!(
+            "INSERT INTO queue_entry (guild_id, position, url, title, requester_id, added_at) VALUES (?, ?, ?, ?, ?, ?)",
+            id,
+            position,
+            url,
+            metadata.title,
+            requester_id,
+            added_at,
+        )
+        .execute(&mut tx)
+        .await?;
+    }
+    tx.commit().await?;
+    Ok(())
+}
+
+/// saves the queues of all guilds, used before shutting down
+pub async fn save_all_queues(
+    ctx: &serenity::Context,
+    database: &sqlx::SqlitePool,
+) -> Result<(), Error> {
+    let manager = get_songbird(ctx).await?;
+    for guild_id in ctx.cache.guilds() {
+        if let Some(call) = manager.get(guild_id) {
+            save_queue(database, guild_id, &call).await?;
+        }
+    }
+    Ok(())
+}
+
+pub async fn get_saved_queue(
+    database: &sqlx::SqlitePool,
+    guild_id: i64,
+) -> Result<Vec<(PlaylistEntry, serenity::UserId)>, Error> {
+    Ok(sqlx::query!(
+        "SELECT url, title, requester_id FROM queue_entry WHERE guild_id = ? ORDER BY position",
+        guild_id
+    )
+    .fetch_all(database)
+    .await?
+    .into_iter()
+    .map(|entry| {
+        (
+            PlaylistEntry {
+                url: entry.url,
+                title: entry.title,
+            },
+            serenity::UserId(entry.requester_id as u64),
+        )
+    })
+    .collect())
+}
+
+pub async fn delete_saved_queue(database: &sqlx::SqlitePool, guild_id: i64) -> Result<(), Error> {
+    sqlx::query!("DELETE FROM queue_entry WHERE guild_id = ?", guild_id)
+        .execute(database)
+        .await?;
+    Ok(())
+}
+
+/// asks in the bound channel of every guild with a saved queue whether it should be restored
+pub async fn offer_queue_restores(ctx: &serenity::Context, data: &Data) -> Result<(), Error> {
+    let guilds = sqlx::query!("SELECT guild_id FROM queue_entry GROUP BY guild_id")
+        .fetch_all(&data.database)
+        .await?;
+    for entry in guilds {
+        let ctx = ctx.clone();
+        let data = data.clone();
+        tokio::spawn(async move {
+            check_result_ayame(
+                offer_queue_restore(&ctx, &data, serenity::GuildId(entry.guild_id as u64)).await,
+            );
+        });
+    }
+    Ok(())
+}
+
+async fn offer_queue_restore(
+    ctx: &serenity::Context,
+    data: &Data,
+    guild_id: serenity::GuildId,
+) -> Result<(), Error> {
+    let database = &data.database;
+    let id = guild_id.0 as i64;
+    let entries = get_saved_queue(database, id).await?;
+    let channel_id = match get_bound_channel_id(database, id).await? {
+        Some(channel_id) => serenity::ChannelId(channel_id),
+        None => return delete_saved_queue(database, id).await,
+    };
+    let color = data.config.color()?;
+    let msg = channel_id
+        .send_message(&ctx.http, |m| {
+            m.embed(|e| {
+                e.title("Restore queue?")
+                    .description(format!(
+                        "there were {} tracks in the queue before the restart",
+                        entries.len()
+                    ))
+                    .color(color)
+            })
+            .components(|c| {
+                c.create_action_row(|ar| {
+                    ar.create_button(|b| {
+                        b.custom_id("queue_restore")
+                            .label("restore")
+                            .style(serenity::ButtonStyle::Success)
+                    })
+                    .create_button(|b| {
+                        b.custom_id("queue_discard")
+                            .label("discard")
+                            .style(serenity::ButtonStyle::Danger)
+                    })
+                })
+            })
+        })
+        .await?;
+
+    while let Some(mci) = serenity::CollectComponentInteraction::new(ctx)
+        .message_id(msg.id)
+        .timeout(RESTORE_TIMEOUT)
+        .await
+    {
+        if mci.data.custom_id != "queue_restore" {
+            break;
+        }
+        let voice_channel = ctx.cache.guild(guild_id).and_then(|guild| {
+            guild
+                .voice_states
+                .get(&mci.user.id)
+                .and_then(|state| state.channel_id)
+        });
+        let voice_channel = match voice_channel {
+            Some(voice_channel) => voice_channel,
+            None => {
+                mci.create_interaction_response(&ctx.http, |ir| {
+                    ir.kind(serenity::InteractionResponseType::ChannelMessageWithSource)
+                        .interaction_response_data(|d| d.content(NOT_IN_VOICE).ephemeral(true))
+                })
+                .await?;
+                continue;
+            }
+        };
+        mci.defer(&ctx.http).await?;
+        let call = join_channel(ctx, data, guild_id, voice_channel).await?;
+        let mut msg = msg.clone();
+        let embed = import_progress_embed("Restoring queue", 0, entries.len(), 0, false, color);
+        msg.edit(ctx, |m| m.set_embed(embed).components(|c| c))
+            .await?;
+        spawn_import(
+            ctx.clone(),
+            data.clone(),
+            guild_id,
+            call,
+            entries,
+            msg,
+            "Restoring queue",
+        );
+        return Ok(());
+    }
+    // discarded or nobody wanted to restore the queue
+    delete_saved_queue(database, id).await?;
+    let _ = msg.delete(&ctx.http).await;
+    Ok(())
+}
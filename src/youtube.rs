@@ -4,7 +4,7 @@ use reqwest::StatusCode;
 use serde::{Deserialize, Serialize};
 use std::string::ToString;
 use strum_macros::Display;
-use url::Url;
+use url::{form_urlencoded, Url};
 
 use crate::error::AyameError as Error;
 
@@ -50,7 +50,6 @@ pub struct Thumbnail {
     height: Option<i32>,
 }
 impl Thumbnail {
-    #[allow(dead_code)]
     pub fn url(&self) -> String {
         self.url.clone()
     }
@@ -64,7 +63,6 @@ impl Thumbnail {
     }
 }
 impl YoutubeResult {
-    #[allow(dead_code)]
     pub fn url(&self) -> String {
         match self.result_type() {
             Type::CHANNEL => format!("https://www.youtube.com/channel/{}", self.id.id),
@@ -74,7 +72,6 @@ impl YoutubeResult {
         }
     }
 
-    #[allow(dead_code)]
     pub fn title(&self) -> String {
         html_escape::decode_html_entities(&self.snippet.title).to_string()
     }
@@ -87,12 +84,10 @@ impl YoutubeResult {
         )
     }
 
-    #[allow(dead_code)]
     pub fn channel_name(&self) -> String {
         html_escape::decode_html_entities(&self.snippet.channel_title).to_string()
     }
 
-    #[allow(dead_code)]
     pub fn small_thumbnail(&self) -> Thumbnail {
         self.snippet.thumbnails.default.clone()
     }
@@ -113,7 +108,6 @@ impl YoutubeResult {
             .into()
     }
 
-    #[allow(dead_code)]
     pub fn result_type(&self) -> Type {
         match self.id.kind.as_ref() {
             "youtube#channel" => Type::CHANNEL,
@@ -129,7 +123,6 @@ pub struct YoutubeResponse {
     items: Vec<YoutubeResult>,
 }
 
-#[allow(dead_code)]
 impl<'a> YoutubeResponse {
     pub fn results(&'a self) -> &'a Vec<YoutubeResult> {
         &self.items
@@ -145,7 +138,6 @@ pub enum Type {
     NONE,
 }
 
-#[allow(dead_code)]
 pub struct YoutubeSearch {
     api_key: String,
     amount: Option<u8>,
@@ -154,7 +146,6 @@ pub struct YoutubeSearch {
 }
 
 impl YoutubeSearch {
-    #[allow(dead_code)]
     pub fn new(api_key: &str) -> YoutubeSearch {
         YoutubeSearch {
             api_key: api_key.to_string(),
@@ -164,13 +155,11 @@ impl YoutubeSearch {
         }
     }
 
-    #[allow(dead_code)]
     pub fn set_amount<'a>(&'a mut self, amount: u8) -> &'a mut YoutubeSearch {
         self.amount = Some(amount);
         self
     }
 
-    #[allow(dead_code)]
     pub fn set_filter<'a>(&'a mut self, result_type: Type) -> &'a mut YoutubeSearch {
         self.result_type = result_type;
         self
@@ -182,11 +171,13 @@ impl YoutubeSearch {
         self
     }
 
-    #[allow(dead_code)]
     fn _build_query(&self, search_term: &str) -> String {
         let mut query = String::new();
         query.push_str("part=snippet");
-        query.push_str(&format!("&q={}", search_term));
+        query.push_str(&format!(
+            "&q={}",
+            form_urlencoded::byte_serialize(search_term.as_bytes()).collect::<String>()
+        ));
         if let Some(amount) = self.amount {
             query.push_str(&format!("&maxResults={}", amount));
         }
@@ -203,7 +194,6 @@ impl YoutubeSearch {
         query
     }
 
-    #[allow(dead_code)]
     pub async fn search(&self, query: &str) -> std::result::Result<YoutubeResponse, Error> {
         let mut url = Url::parse("https://www.googleapis.com/youtube/v3/search").unwrap();
         let query = self._build_query(query);
@@ -231,7 +221,6 @@ impl YoutubeSearch {
     }
 }
 
-#[allow(dead_code)]
 pub fn hyperlink_result(result: &YoutubeResult) -> String {
     if let Type::CHANNEL = result.result_type() {
         return format!("[{}]({})", result.title(), result.url(),);